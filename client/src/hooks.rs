@@ -0,0 +1,73 @@
+use std::{fmt::Display, future::Future};
+
+use serde::de::DeserializeOwned;
+use yew::{platform::spawn_local, prelude::*};
+
+pub const API_URL: &str = "http://localhost:3000";
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ApiState<T> {
+    #[default]
+    Loading,
+    Success(T),
+    Error(String),
+}
+
+impl<T, E: Display> From<Result<T, E>> for ApiState<T> {
+    fn from(value: Result<T, E>) -> Self {
+        match value {
+            Ok(data) => ApiState::Success(data),
+            Err(e) => ApiState::Error(e.to_string()),
+        }
+    }
+}
+
+pub async fn fetch_json<T: DeserializeOwned>(path: &str) -> Result<T, reqwest::Error> {
+    reqwest::get(format!("{API_URL}{path}")).await?.json().await
+}
+
+//Runs the request once when the component is mounted, the returned state
+//starts as Loading and ends up as either Success or Error
+#[hook]
+pub fn use_api<T, E, F, Fut>(request: F) -> UseStateHandle<ApiState<T>>
+where
+    T: 'static,
+    E: Display + 'static,
+    F: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = Result<T, E>> + 'static,
+{
+    let state = use_state(ApiState::default);
+
+    {
+        let state = state.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    state.set(ApiState::from(request().await));
+                });
+                || {}
+            },
+            (),
+        );
+    }
+
+    state
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_api_state_from_result() {
+        let state: ApiState<i32> = ApiState::default();
+        assert_eq!(state, ApiState::Loading);
+
+        let state = ApiState::from(Ok::<_, String>(3));
+        assert_eq!(state, ApiState::Success(3));
+
+        let state = ApiState::<i32>::from(Err("Unknown room id"));
+        assert_eq!(state, ApiState::Error("Unknown room id".to_string()));
+    }
+}
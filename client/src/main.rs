@@ -1,10 +1,11 @@
-#![feature(async_closure)]
-#![feature(let_chains)]
+mod hooks;
+
+use std::rc::Rc;
 
 use gloo_console::log;
-use reqwest::header;
-use serde_json::to_string;
-use web_sys::{HtmlElement, HtmlInputElement, MessageEvent};
+use hooks::{fetch_json, use_api, ApiState};
+use types::net::RoomsListResponse;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 
 use yew::{platform::spawn_local, prelude::*};
 #[function_component]
@@ -79,20 +80,84 @@ fn Button() -> Html {
     html! {<p>{"toto"}</p>}
 }
 
-const GAMES: &[(&str, &str)] = &[("rps", "Rock-Paper-Scissor"), ("chess", "Chess")];
+const GAMES: &[(&str, &str)] = &[
+    ("rps", "Rock-Paper-Scissor"),
+    ("rpsls", "Rock-Paper-Scissors-Lizard-Spock"),
+    ("chess", "Chess"),
+];
 
 #[function_component]
 fn Lobby() -> Html {
+    let rooms_list = use_api(|| fetch_json::<RoomsListResponse>("/rooms/list"));
+
     html! {
         <div>
             <CreateNewRoomForm/>
+            {
+                match &*rooms_list {
+                    ApiState::Loading => html! {<p>{ "Loading rooms..." }</p>},
+                    ApiState::Success(rooms_list) => rooms_list.rooms.iter().map(|room| {
+                        html!{<p>{ room.name.clone() }</p>}
+                    }).collect::<Html>(),
+                    ApiState::Error(e) => html! {<p class="help is-danger">{ e }</p>},
+                }
+            }
         </div>
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+struct RoomFormState {
+    game_id: String,
+    room_name: String,
+}
+
+impl Default for RoomFormState {
+    fn default() -> Self {
+        Self {
+            game_id: GAMES[0].0.to_string(),
+            room_name: String::new(),
+        }
+    }
+}
+
+impl RoomFormState {
+    fn is_room_name_valid(&self) -> bool {
+        !self.room_name.trim().is_empty()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.is_room_name_valid() && GAMES.iter().any(|(id, _)| *id == self.game_id)
+    }
+}
+
+enum RoomFormAction {
+    SelectGame(String),
+    SetRoomName(String),
+}
+
+impl Reducible for RoomFormState {
+    type Action = RoomFormAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut state = (*self).clone();
+
+        match action {
+            RoomFormAction::SelectGame(game_id) => {
+                if GAMES.iter().any(|(id, _)| *id == game_id) {
+                    state.game_id = game_id;
+                }
+            }
+            RoomFormAction::SetRoomName(room_name) => state.room_name = room_name,
+        }
+
+        state.into()
+    }
+}
+
 #[function_component]
 fn CreateNewRoomForm() -> Html {
-    let game_id = use_state(|| GAMES[0].0.to_string());
+    let form = use_reducer(RoomFormState::default);
 
     let onclick = {
         move |_| {
@@ -100,43 +165,39 @@ fn CreateNewRoomForm() -> Html {
         }
     };
 
-    /*let ongamechanged = {
-        move |_| {
-            todo!();
-        }
-    };*/
-
     let ongamechanged = {
-        let game_id = game_id.clone();
-        Callback::from(move |e: yew::html::onchange::Event| {
-            log!(e.clone());
-
-            let input = e.target_unchecked_into::<HtmlElement>();
-            log!("titi", input.clone(), input.node_value());
+        let form = form.clone();
+        Callback::from(move |e: Event| {
+            let select = e.target_unchecked_into::<HtmlSelectElement>();
+            form.dispatch(RoomFormAction::SelectGame(select.value()));
+        })
+    };
 
-            /*if input.is_some() {
-                game_id.set(input.unwrap().value());
-                log!("new value for game_id : ", (*game_id).clone());
-            }*/
+    let onroomnamechanged = {
+        let form = form.clone();
+        Callback::from(move |e: InputEvent| {
+            let input = e.target_unchecked_into::<HtmlInputElement>();
+            form.dispatch(RoomFormAction::SetRoomName(input.value()));
         })
     };
 
     html! {
         <form>
 
-            <select list="games" disabled=false required=true /*onselect = {ongamechanged.clone()}*/  onchange = {ongamechanged}>
+            <input type="text" placeholder="Room name" value={form.room_name.clone()} oninput={onroomnamechanged}/>
+            if !form.is_room_name_valid() {
+                <p class="help is-danger">{ "The room needs a name" }</p>
+            }
+
+            <select list="games" disabled=false required=true onchange={ongamechanged}>
             {
-                GAMES.into_iter().map(|(id, name)| {
-                    if id.to_string() == *game_id {
-                        html!{<option value={id.to_string()} selected=true>{name.to_string()}</option>}
-                    } else {
-                        html!{<option value={id.to_string()} >{name.to_string()}</option>}
-                    }
+                GAMES.iter().map(|(id, name)| {
+                    html!{<option value={id.to_string()} selected={*id == form.game_id}>{name.to_string()}</option>}
                 }).collect::<Html>()
             }
             </select>
 
-            <button {onclick}>{ "Create new game room" }</button>
+            <button {onclick} disabled={!form.is_valid()}>{ "Create new game room" }</button>
 
         </form>
     }
@@ -145,3 +206,34 @@ fn CreateNewRoomForm() -> Html {
 fn main() {
     yew::Renderer::<Lobby>::new().render();
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_select_game_updates_state() {
+        let form = Rc::new(RoomFormState::default());
+        assert_eq!(form.game_id, "rps");
+
+        let form = form.reduce(RoomFormAction::SelectGame("chess".to_string()));
+        assert_eq!(form.game_id, "chess");
+
+        //unknown games are ignored
+        let form = form.reduce(RoomFormAction::SelectGame("poker".to_string()));
+        assert_eq!(form.game_id, "chess");
+    }
+
+    #[test]
+    fn test_room_name_is_required() {
+        let form = Rc::new(RoomFormState::default());
+        assert!(!form.is_valid());
+
+        let form = form.reduce(RoomFormAction::SetRoomName("   ".to_string()));
+        assert!(!form.is_valid());
+
+        let form = form.reduce(RoomFormAction::SetRoomName("my room".to_string()));
+        assert!(form.is_valid());
+    }
+}
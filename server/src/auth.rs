@@ -0,0 +1,31 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use types::error::GameError;
+
+use crate::error::ApiError;
+
+//Session token of the player making the request, sent in an
+//`Authorization: Bearer <token>` header
+pub struct SessionToken(pub String);
+
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    authorization.strip_prefix("Bearer ")
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SessionToken {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer_token)
+            .map(|token| Self(token.to_string()))
+            .ok_or(ApiError(GameError::InvalidToken))
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use types::Timestamp;
+
+#[derive(Debug, Default, Clone)]
+pub enum Clock {
+    #[default]
+    System,
+    //Frozen time, moved forward by hand in tests
+    #[cfg_attr(not(test), allow(dead_code))]
+    Manual(Timestamp),
+}
+
+impl Clock {
+    //Milliseconds since the unix epoch
+    pub fn now(&self) -> Timestamp {
+        match self {
+            Clock::System => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as Timestamp)
+                .unwrap_or_default(),
+            Clock::Manual(now) => *now,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn advance(&mut self, millis: Timestamp) {
+        if let Clock::Manual(now) = self {
+            *now += millis;
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use anyhow::{Context, Result};
+use types::Timestamp;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    //Interface and port the HTTP server listens on, 127.0.0.1 keeps it local
+    pub bind_addr: SocketAddr,
+    //How long the seat of a player who left a room is kept for them, 0 disables it
+    pub rejoin_grace_ms: Timestamp,
+    //How long matchmaking waits for someone from the same region
+    pub cross_region_wait_ms: Timestamp,
+    //How many rooms a single player can host at the same time
+    pub max_hosted_rooms: usize,
+    //Whether guests can play without creating a persistent player
+    pub allow_ephemeral_players: bool,
+    //Players not heard from for that long are not counted as active
+    pub idle_after_ms: Timestamp,
+    //Players of a running game not heard from for that long forfeit it, 0 disables it
+    pub disconnect_after_ms: Timestamp,
+    //How far back matchmaking games are looked at to estimate wait times
+    pub match_rate_window_ms: Timestamp,
+    //How long an ended game can still be fetched before it is archived
+    pub ended_game_grace_ms: Timestamp,
+    //Players who sent at least that many moves, with at least that share of
+    //them rejected, are listed as suspicious to the admins
+    pub suspicious_min_moves: usize,
+    pub suspicious_rejection_rate: f64,
+    //How many players /leaderboard lists when the request doesn't say
+    pub leaderboard_size: usize,
+    //Bearer token of the /admin requests, they are all refused without one
+    pub admin_token: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 3000),
+            rejoin_grace_ms: 0,
+            cross_region_wait_ms: 30_000,
+            max_hosted_rooms: 3,
+            allow_ephemeral_players: true,
+            idle_after_ms: 60_000,
+            disconnect_after_ms: 120_000,
+            match_rate_window_ms: 60_000,
+            ended_game_grace_ms: 300_000,
+            suspicious_min_moves: 10,
+            suspicious_rejection_rate: 0.5,
+            leaderboard_size: 10,
+            admin_token: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    //BOARDGAMES_HOST and BOARDGAMES_PORT override the default bind address,
+    //BOARDGAMES_ADMIN_TOKEN opens the admin routes
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut config = Self::default();
+
+        if let Some(host) = lookup("BOARDGAMES_HOST") {
+            config
+                .bind_addr
+                .set_ip(host.parse().context("Invalid BOARDGAMES_HOST")?);
+        }
+        if let Some(port) = lookup("BOARDGAMES_PORT") {
+            config
+                .bind_addr
+                .set_port(port.parse().context("Invalid BOARDGAMES_PORT")?);
+        }
+
+        config.admin_token = lookup("BOARDGAMES_ADMIN_TOKEN");
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_bind_addr_from_env() {
+        let env = HashMap::from([
+            ("BOARDGAMES_HOST", "127.0.0.1"),
+            ("BOARDGAMES_PORT", "8080"),
+            ("BOARDGAMES_ADMIN_TOKEN", "admin secret"),
+        ]);
+        let config =
+            ServerConfig::from_lookup(|key| env.get(key).map(|value| value.to_string())).unwrap();
+        assert_eq!(config.bind_addr, "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.admin_token.as_deref(), Some("admin secret"));
+
+        let config = ServerConfig::from_lookup(|_| None).unwrap();
+        assert_eq!(config.bind_addr, "0.0.0.0:3000".parse().unwrap());
+        assert_eq!(config.admin_token, None);
+
+        assert!(ServerConfig::from_lookup(|_| Some("not an address".to_string())).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use axum::{
+    extract::rejection::{JsonRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use types::error::{ErrorResponse, GameError};
+
+//GameError lives in the types crate, this wrapper is what lets handlers turn it into a response
+pub struct ApiError(pub GameError);
+
+impl From<GameError> for ApiError {
+    fn from(value: GameError) -> Self {
+        Self(value)
+    }
+}
+
+//How an error is reported over HTTP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    //The request is fine, but not in the current state of the room or game
+    Conflict,
+    Forbidden,
+    BadRequest,
+    Unauthorized,
+    TooManyRequests,
+}
+
+impl ErrorKind {
+    //Every error is listed, a new one has to be given a kind
+    pub fn of(error: GameError) -> Self {
+        match error {
+            GameError::UnknownPlayer
+            | GameError::UnknownRoom
+            | GameError::UnknownGame
+            | GameError::UnknownRound
+            | GameError::NotFound
+            | GameError::NoRunningGame => ErrorKind::NotFound,
+            GameError::NameTaken
+            | GameError::AlreadyInRoom
+            | GameError::RoomFull
+            | GameError::AlreadyLeftRoom
+            | GameError::GameKindMismatch
+            | GameError::NotEnoughSeats
+            | GameError::RoomNotFull
+            | GameError::AlreadyInGame
+            | GameError::GameNotRunning
+            | GameError::GamePaused
+            | GameError::ActionLockedIn
+            | GameError::StaleVersion
+            | GameError::TooManyRooms
+            | GameError::GameStillRunning
+            | GameError::NotYourTurn
+            | GameError::PlayerGone
+            | GameError::PlayersNotReady => ErrorKind::Conflict,
+            GameError::NotInRoom
+            | GameError::NotHost
+            | GameError::NotInGame
+            | GameError::PlayerEliminated
+            | GameError::PlayerForfeited
+            | GameError::EphemeralPlayersDisabled
+            | GameError::WrongPassword => ErrorKind::Forbidden,
+            GameError::SelfMerge
+            | GameError::NoMoves
+            | GameError::ActionNotAvailable
+            | GameError::InvalidWinTable
+            | GameError::SelfFriend
+            | GameError::NoHumanPlayer
+            | GameError::IllegalMove
+            | GameError::NotEnoughPlayers
+            | GameError::InvalidRequest
+            | GameError::InvalidPlayerCount => ErrorKind::BadRequest,
+            GameError::InvalidToken => ErrorKind::Unauthorized,
+            GameError::ActionCooldown => ErrorKind::TooManyRequests,
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Conflict => StatusCode::CONFLICT,
+            ErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ErrorKind::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorKind::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = ErrorKind::of(self.0).status();
+
+        (status, Json(ErrorResponse::from(self.0))).into_response()
+    }
+}
+
+//A request axum couldn't parse, with axum's explanation as the message
+//instead of the plain text body axum would send
+pub struct InvalidRequest(String);
+
+impl From<JsonRejection> for InvalidRequest {
+    fn from(value: JsonRejection) -> Self {
+        Self(value.body_text())
+    }
+}
+
+impl From<QueryRejection> for InvalidRequest {
+    fn from(value: QueryRejection) -> Self {
+        Self(value.body_text())
+    }
+}
+
+impl IntoResponse for InvalidRequest {
+    fn into_response(self) -> Response {
+        let error = ErrorResponse {
+            code: GameError::InvalidRequest,
+            message: self.0,
+        };
+
+        (ErrorKind::of(error.code).status(), Json(error)).into_response()
+    }
+}
@@ -0,0 +1,13 @@
+use axum::extract::{FromRequest, FromRequestParts};
+
+use crate::error::InvalidRequest;
+
+//Same as axum's Json and Query, but a malformed request is answered with an
+//INVALID_REQUEST error like any other
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(InvalidRequest))]
+pub struct ApiJson<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(InvalidRequest))]
+pub struct ApiQuery<T>(pub T);
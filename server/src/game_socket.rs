@@ -0,0 +1,212 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{IntoResponse, Response},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use types::{
+    net::{GameSocketQuery, LaunchGetGameResponse},
+    GameData, PlayerId,
+};
+
+use crate::{error::ApiError, extract::ApiQuery, ServerContext};
+
+//Live updates of a game, instead of polling /game/data. The game is sent
+//right away, then again every time it changes, as /game/data would return
+//it. The socket is closed once the game is archived. Anything the client
+//sends is ignored, the player counts as connected while it is open
+pub async fn game_socket(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(game_socket_query): ApiQuery<GameSocketQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let GameSocketQuery {
+        player_id,
+        game_id,
+        token,
+    } = game_socket_query;
+
+    if let Err(e) = ctx.authenticate(player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    //Followed before upgrading, so a game the player can't see is refused
+    //with a regular error response
+    match ctx.follow_game(player_id, game_id).await {
+        Ok((game_data, updates)) => ws.on_upgrade(move |socket| {
+            relay_game_updates(socket, ctx, player_id, game_data, updates)
+        }),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+//Well within the time a silent player is given before forfeiting
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+//Returns once the connection is closed, whichever side closed it
+async fn relay_game_updates(
+    mut socket: WebSocket,
+    ctx: Arc<ServerContext>,
+    player_id: PlayerId,
+    game_data: GameData,
+    mut updates: broadcast::Receiver<GameData>,
+) {
+    let mut sent_version = game_data.version;
+    if send_game(&mut socket, game_data, player_id).await.is_err() {
+        return;
+    }
+
+    let mut heartbeats = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = heartbeats.tick() => {
+                //Fails once the player is gone, along with their game
+                let _ = ctx.heartbeat(player_id).await;
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => (),
+            },
+            update = updates.recv() => match update {
+                //Moves are published once their game is unlocked, an update
+                //can come after a newer one which was already sent
+                Ok(game_data) if game_data.version > sent_version => {
+                    sent_version = game_data.version;
+                    if send_game(&mut socket, game_data, player_id).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => (),
+                //Each update is the whole game, the next one catches up
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+async fn send_game(
+    socket: &mut WebSocket,
+    game_data: GameData,
+    player_id: PlayerId,
+) -> Result<(), axum::Error> {
+    let response = LaunchGetGameResponse::for_player(game_data, Some(player_id));
+    socket
+        .send(Message::Text(serde_json::to_string(&response).unwrap()))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
+    use types::{ActionKind, GameId, RoomVisibility};
+
+    use super::*;
+
+    type ClientSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    async fn next_game(socket: &mut ClientSocket) -> serde_json::Value {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let tungstenite::Message::Text(text) = socket.next().await.unwrap().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_game_socket_follows_moves() {
+        let ctx = Arc::new(ServerContext::new());
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(crate::router(ctx.clone()).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let url = |player_id: PlayerId, game_id: GameId, token: &str| {
+            format!("ws://{addr}/game/ws?player_id={player_id}&game_id={game_id}&token={token}")
+        };
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let bob_token = ctx.session_token(bob.id).await.unwrap();
+        assert!(
+            tokio_tungstenite::connect_async(url(alice.id, game_data.id, &bob_token))
+                .await
+                .is_err()
+        );
+        assert!(
+            tokio_tungstenite::connect_async(url(alice.id, game_data.id + 1, &alice_token))
+                .await
+                .is_err()
+        );
+
+        let (mut alice_socket, _) =
+            tokio_tungstenite::connect_async(url(alice.id, game_data.id, &alice_token))
+                .await
+                .unwrap();
+        let (mut bob_socket, _) =
+            tokio_tungstenite::connect_async(url(bob.id, game_data.id, &bob_token))
+                .await
+                .unwrap();
+
+        //Both get the game as it is when they connect
+        for socket in [&mut alice_socket, &mut bob_socket] {
+            let game = next_game(socket).await;
+            assert_eq!(game["id"], game_data.id);
+            assert_eq!(game["waiting_count"], 2);
+        }
+
+        ctx.play_round(alice.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+        let game = next_game(&mut alice_socket).await;
+        assert_eq!(game["waiting_count"], 1);
+        assert_eq!(game["your_current_action"], "Rock");
+        let game = next_game(&mut bob_socket).await;
+        assert_eq!(game["waiting_count"], 1);
+        assert_eq!(game["your_current_action"], serde_json::Value::Null);
+
+        ctx.play_round(bob.id, game_data.id, ActionKind::Scissors, None, None)
+            .await
+            .unwrap();
+        for socket in [&mut alice_socket, &mut bob_socket] {
+            let game = next_game(socket).await;
+            assert_eq!(game["round_history"].as_array().unwrap().len(), 1);
+            assert_eq!(game["players"][0][1], 1);
+        }
+    }
+}
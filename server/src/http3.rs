@@ -0,0 +1,269 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Result;
+use bytes::Bytes;
+use h3::server::RequestStream;
+use http::{Request, Response, StatusCode};
+use quinn::crypto::rustls::QuicServerConfig;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use types::{
+    error::{ErrorResponse, GameError},
+    net::{LaunchGetGameResponse, PlayRoundQuery},
+};
+
+use crate::{auth::bearer_token, error::ErrorKind, ServerContext};
+
+//Experimental HTTP/3 transport for low latency play, mirroring `/game/play`.
+//The endpoint uses a self-signed certificate generated at startup.
+pub fn bind(addr: SocketAddr) -> Result<(quinn::Endpoint, CertificateDer<'static>)> {
+    let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+    let cert = certified_key.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+        certified_key.key_pair.serialize_der(),
+    ));
+
+    let mut tls_config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_protocol_versions(&[&rustls::version::TLS13])?
+    .with_no_client_auth()
+    .with_single_cert(vec![cert.clone()], key)?;
+    tls_config.alpn_protocols = vec![b"h3".to_vec()];
+
+    let server_config =
+        quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls_config)?));
+
+    Ok((quinn::Endpoint::server(server_config, addr)?, cert))
+}
+
+pub async fn serve(endpoint: quinn::Endpoint, ctx: Arc<ServerContext>) {
+    while let Some(incoming) = endpoint.accept().await {
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(incoming, ctx).await {
+                println!("HTTP/3 connection error : {e}");
+            }
+        });
+    }
+}
+
+async fn handle_connection(incoming: quinn::Incoming, ctx: Arc<ServerContext>) -> Result<()> {
+    let connection = incoming.await?;
+    let mut h3_connection: h3::server::Connection<_, Bytes> =
+        h3::server::Connection::new(h3_quinn::Connection::new(connection)).await?;
+
+    while let Some(resolver) = h3_connection.accept().await? {
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            if let Ok((request, stream)) = resolver.resolve_request().await {
+                if let Err(e) = handle_request(request, stream, ctx).await {
+                    println!("HTTP/3 request error : {e}");
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn handle_request<S>(
+    request: Request<()>,
+    mut stream: RequestStream<S, Bytes>,
+    ctx: Arc<ServerContext>,
+) -> Result<()>
+where
+    S: h3::quic::BidiStream<Bytes>,
+{
+    let (status, body) = match request.uri().path() {
+        "/game/play" => {
+            let token = request
+                .headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(bearer_token)
+                .unwrap_or_default();
+            play_round(request.uri().query().unwrap_or_default(), token, &ctx).await
+        }
+        path => error_response(ErrorResponse {
+            code: GameError::NotFound,
+            message: format!("No route for {path}"),
+        }),
+    };
+
+    stream
+        .send_response(
+            Response::builder()
+                .status(status)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(())?,
+        )
+        .await?;
+    stream.send_data(Bytes::from(body)).await?;
+    stream.finish().await?;
+
+    Ok(())
+}
+
+async fn play_round(query: &str, token: &str, ctx: &ServerContext) -> (StatusCode, String) {
+    let play_round_query: PlayRoundQuery = match serde_urlencoded::from_str(query) {
+        Ok(play_round_query) => play_round_query,
+        Err(e) => {
+            return error_response(ErrorResponse {
+                code: GameError::InvalidRequest,
+                message: e.to_string(),
+            })
+        }
+    };
+
+    if let Err(e) = ctx.authenticate(play_round_query.player_id, token).await {
+        return error_response(ErrorResponse::from(e));
+    }
+
+    match ctx
+        .play_round(
+            play_round_query.player_id,
+            play_round_query.game_id,
+            play_round_query.action,
+            play_round_query.expected_version,
+            play_round_query.idempotency_key,
+        )
+        .await
+    {
+        Ok(game_data) => match serde_json::to_string(
+            &LaunchGetGameResponse::for_player(game_data, Some(play_round_query.player_id))
+                .with_scores_map(play_round_query.scores_as_map),
+        ) {
+            Ok(body) => (StatusCode::OK, body),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        },
+        Err(e) => error_response(ErrorResponse::from(e)),
+    }
+}
+
+//Same body and status as over HTTP/1
+fn error_response(error: ErrorResponse) -> (StatusCode, String) {
+    //axum and h3 don't use the same version of the http crate
+    let status = StatusCode::from_u16(ErrorKind::of(error.code).status().as_u16()).unwrap();
+
+    (status, serde_json::to_string(&error).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use anyhow::anyhow;
+    use bytes::Buf;
+
+    use super::*;
+
+    async fn get(
+        client: &quinn::Endpoint,
+        addr: SocketAddr,
+        path: &str,
+        token: &str,
+    ) -> Result<(StatusCode, String)> {
+        let connection = client.connect(addr, "localhost")?.await?;
+        let (mut driver, mut send_request) =
+            h3::client::new(h3_quinn::Connection::new(connection)).await?;
+        tokio::spawn(async move { std::future::poll_fn(|cx| driver.poll_close(cx)).await });
+
+        let request = Request::get(format!("https://localhost{path}"))
+            .header(http::header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(())?;
+        let mut stream = send_request.send_request(request).await?;
+        stream.finish().await?;
+
+        let response = stream.recv_response().await?;
+        let mut body = Vec::new();
+        while let Some(mut chunk) = stream.recv_data().await? {
+            while chunk.has_remaining() {
+                let bytes = chunk.chunk();
+                body.extend_from_slice(bytes);
+                let len = bytes.len();
+                chunk.advance(len);
+            }
+        }
+
+        Ok((
+            response.status(),
+            String::from_utf8(body).map_err(|e| anyhow!(e))?,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_http3_play_resolves_round() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                types::RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let (endpoint, cert) = bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+        tokio::spawn(serve(endpoint, ctx.clone()));
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert).unwrap();
+        let mut tls_config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        tls_config.alpn_protocols = vec![b"h3".to_vec()];
+
+        let mut client = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        client.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+            quinn::crypto::rustls::QuicClientConfig::try_from(tls_config).unwrap(),
+        )));
+
+        let (status, _) = get(
+            &client,
+            addr,
+            &format!(
+                "/game/play?player_id={}&game_id={}&action=Rock",
+                alice.id, game_data.id
+            ),
+            &ctx.session_token(alice.id).await.unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = get(
+            &client,
+            addr,
+            &format!(
+                "/game/play?player_id={}&game_id={}&action=Scissors",
+                bob.id, game_data.id
+            ),
+            &ctx.session_token(bob.id).await.unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["round_history"].as_array().unwrap().len(), 1);
+    }
+}
@@ -1,26 +1,51 @@
+mod auth;
+mod clock;
+mod config;
+mod error;
+mod extract;
+mod game_socket;
+#[cfg(feature = "http3")]
+mod http3;
+mod presence;
+mod room_events;
 mod server;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use auth::SessionToken;
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
+use config::ServerConfig;
 use server::ServerData;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tower_http::cors::CorsLayer;
 use types::{
     net::{
-        GetGameQuery, JoinGetLeaveRoomQuery, JoinGetRoomResponse, LaunchGameQuery,
-        LaunchGetGameResponse, NewPlayerQuery, NewPlayerResponse, NewRoomQuery, NewRoomResponse,
-        PlayRoundQuery, RoomsListResponse,
+        AnalyticsQuery, FriendQuery, FriendsResponse, GamePublicData, GameSummary, GamesBatchQuery,
+        GamesBatchResponse, GamesListQuery, GamesListResponse, GetGameQuery, GetRoundQuery,
+        HeartbeatQuery, JoinGetLeaveRoomQuery, JoinGetRoomResponse, KickPlayerQuery,
+        LaunchGameQuery, LaunchGetGameResponse, LaunchStatusResponse, LaunchWithBotsQuery,
+        LeaderboardQuery, LeaderboardResponse, LegalActionsResponse, MatchmakingEstimateResponse,
+        MatchmakingQuery, MatchmakingResponse, MergeRoomsQuery, MetricsResponse, NewPlayerQuery,
+        NewPlayerResponse, NewRoomQuery, NewRoomResponse, NotificationsQuery,
+        NotificationsResponse, PlayBatchQuery, PlayRoundQuery, PlayerFullData, PlayerStatsQuery,
+        PresenceEvent, ReadyQuery, RematchQuery, RenamePlayerQuery, ReplayPracticeQuery,
+        RoomGameQuery, RoomGameResponse, RoomsListQuery, RoomsListResponse, SpectateGameQuery,
+        SpectateGameResponse, SuspiciousPlayersResponse,
     },
-    ActionKind, GameData, GameId, GameSettings, PlayerData, PlayerId, RoomData, RoomId,
+    ActionKind, BotStrategy, GameData, GameId, GameSettings, GameStatus, LaunchReadiness,
+    MoveStats, Notification, PlayerAnalytics, PlayerData, PlayerId, PlayerStats, RoomData, RoomId,
+    RoomVisibility, RoundData, Timestamp,
 };
 
-use anyhow::Result;
+use error::ApiError;
+use extract::{ApiJson, ApiQuery};
+use types::error::{ErrorResponse, GameError};
 
 trait OptionResponse {
     fn or_not_found(self, type_name: &str) -> Response;
@@ -40,12 +65,32 @@ where
 
 struct ServerContext {
     server_data: Mutex<ServerData>,
+    //Every presence WebSocket listens to this
+    presence_events: broadcast::Sender<PresenceEvent>,
+    //Every change to a game is sent to the WebSockets following it. The
+    //sender of a game is made when someone first follows it, and dropped
+    //once the game is archived
+    game_updates: std::sync::Mutex<HashMap<GameId, broadcast::Sender<GameData>>>,
+    //Same for the players waiting in a room, None is sent once the room is
+    //gone, because it emptied or its game was launched
+    room_updates: std::sync::Mutex<HashMap<RoomId, broadcast::Sender<Option<RoomData>>>>,
 }
 
 impl ServerContext {
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    pub fn with_config(config: ServerConfig) -> Self {
+        let mut server_data = ServerData::default();
+        server_data.config = config;
+
         Self {
-            server_data: Mutex::new(ServerData::default()),
+            server_data: Mutex::new(server_data),
+            presence_events: broadcast::channel(64).0,
+            game_updates: std::sync::Mutex::default(),
+            room_updates: std::sync::Mutex::default(),
         }
     }
 
@@ -59,226 +104,3243 @@ impl ServerContext {
         func(&mut server_data)
     }
 
-    pub async fn create_player_with_name(&self, player_name: String) -> Result<PlayerData> {
-        self.with_data_mut(|server_data| server_data.create_player_with_name(player_name))
+    pub async fn create_player_with_region(
+        &self,
+        player_name: String,
+        region: Option<String>,
+    ) -> Result<PlayerData, GameError> {
+        self.with_data_mut(|server_data| server_data.create_player_with_region(player_name, region))
             .await
     }
 
-    pub async fn get_rooms_list(&self) -> Vec<RoomData> {
-        self.with_data(ServerData::get_rooms_list).await
+    pub async fn session_token(&self, player_id: PlayerId) -> Result<String, GameError> {
+        self.with_data(|server_data| {
+            server_data
+                .session_token(player_id)
+                .ok_or(GameError::UnknownPlayer)
+        })
+        .await
     }
 
-    pub async fn create_room(
+    //Authenticated requests count as a heartbeat
+    pub async fn authenticate(&self, player_id: PlayerId, token: &str) -> Result<(), GameError> {
+        self.with_data_mut(|server_data| {
+            server_data.authenticate(player_id, token)?;
+            server_data.heartbeat(player_id)
+        })
+        .await
+    }
+
+    //Subscribes before coming online, so the connection also hears about
+    //itself and about anyone coming online right after
+    pub async fn connect_presence(
         &self,
         player_id: PlayerId,
-        room_name: String,
-        settings: Option<GameSettings>,
-    ) -> Result<RoomData> {
-        self.with_data_mut(|server_data| server_data.create_room(player_id, room_name, settings))
+    ) -> Result<broadcast::Receiver<PresenceEvent>, GameError> {
+        let presence_events = self.presence_events.subscribe();
+
+        let event = self
+            .with_data_mut(|server_data| server_data.connect_presence(player_id))
+            .await?;
+        if let Some(event) = event {
+            //Nobody listening is not an error
+            let _ = self.presence_events.send(event);
+        }
+
+        Ok(presence_events)
+    }
+
+    //The game as the player sees it now, and every change made to it from
+    //then on. The player must be in the game
+    pub async fn follow_game(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<(GameData, broadcast::Receiver<GameData>), GameError> {
+        //Subscribing while holding the server lock means a move played in
+        //between is either in the snapshot or sent afterwards
+        self.with_data(|server_data| {
+            let game_data = server_data.get_game_data(player_id, game_id)?;
+            let updates = self
+                .game_updates
+                .lock()
+                .unwrap()
+                .entry(game_id)
+                .or_insert_with(|| broadcast::channel(16).0)
+                .subscribe();
+            Ok((game_data, updates))
+        })
+        .await
+    }
+
+    fn publish_game_update(&self, game_data: &GameData) {
+        if let Some(updates) = self.game_updates.lock().unwrap().get(&game_data.id) {
+            //Nobody following the game is not an error
+            let _ = updates.send(game_data.clone());
+        }
+    }
+
+    //The room as it is now, and every change made to it from then on. The
+    //player must be in the room
+    pub async fn follow_room(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<(RoomData, broadcast::Receiver<Option<RoomData>>), GameError> {
+        self.with_data(|server_data| {
+            let room_data = server_data.get_room_data(player_id, room_id)?;
+            let updates = self
+                .room_updates
+                .lock()
+                .unwrap()
+                .entry(room_id)
+                .or_insert_with(|| broadcast::channel(16).0)
+                .subscribe();
+            Ok((room_data, updates))
+        })
+        .await
+    }
+
+    //Called with the server lock held, so the updates of a room are sent in
+    //the order they were made
+    fn publish_room_update(&self, server_data: &ServerData, room_id: RoomId) {
+        let mut room_updates = self.room_updates.lock().unwrap();
+        let Some(updates) = room_updates.get(&room_id) else {
+            return;
+        };
+
+        let room_data = server_data.rooms.get(&room_id).cloned();
+        let room_closed = room_data.is_none();
+        //Nobody following the room is not an error
+        let _ = updates.send(room_data);
+        if room_closed {
+            room_updates.remove(&room_id);
+        }
+    }
+
+    pub async fn disconnect_presence(&self, player_id: PlayerId) {
+        let event = self
+            .with_data_mut(|server_data| server_data.disconnect_presence(player_id))
+            .await;
+        if let Some(event) = event {
+            let _ = self.presence_events.send(event);
+        }
+    }
+
+    pub async fn online_players(&self) -> Vec<PlayerData> {
+        self.with_data(ServerData::online_players).await
+    }
+
+    pub async fn rename_player(
+        &self,
+        player_id: PlayerId,
+        new_name: String,
+    ) -> Result<PlayerData, GameError> {
+        let (player_data, games) = self
+            .with_data_mut(|server_data| {
+                let player_data = server_data.rename_player(player_id, new_name)?;
+                Ok::<_, GameError>((player_data, server_data.game_handles()))
+            })
+            .await?;
+
+        //Each game is renamed holding only its own lock, off the async workers
+        let renamed_player = player_data.clone();
+        let renamed_games = tokio::task::spawn_blocking(move || {
+            games
+                .iter()
+                .filter_map(|game| {
+                    let mut game_data = game.lock().unwrap();
+                    ServerData::rename_in_game(&mut game_data, &renamed_player)
+                        .then(|| game_data.clone())
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+
+        for game_data in &renamed_games {
+            self.publish_game_update(game_data);
+        }
+
+        Ok(player_data)
+    }
+
+    pub async fn matchmake(&self, player_id: PlayerId) -> Result<Option<GameData>, GameError> {
+        self.with_data_mut(|server_data| server_data.matchmake(player_id))
             .await
     }
 
-    pub async fn join_room(&self, player_id: PlayerId, room_id: RoomId) -> Result<RoomData> {
-        self.with_data_mut(|server_data| server_data.join_room(player_id, room_id))
+    pub async fn create_ephemeral_player(
+        &self,
+        player_name: String,
+    ) -> Result<PlayerData, GameError> {
+        self.with_data_mut(|server_data| server_data.create_ephemeral_player(player_name))
             .await
     }
 
-    pub async fn leave_room(&self, player_id: PlayerId, room_id: RoomId) -> Result<()> {
-        self.with_data_mut(|server_data| server_data.leave_room(player_id, room_id))
+    pub async fn estimate_wait(&self, player_id: PlayerId) -> Result<Timestamp, GameError> {
+        self.with_data(|server_data| server_data.estimate_wait(player_id))
             .await
     }
 
-    pub async fn get_room_data(&self, player_id: PlayerId, room_id: RoomId) -> Result<RoomData> {
-        self.with_data(|server_data| server_data.get_room_data(player_id, room_id))
+    pub async fn player_analytics(
+        &self,
+        player_id: PlayerId,
+    ) -> Result<PlayerAnalytics, GameError> {
+        self.with_data(|server_data| server_data.player_analytics(player_id))
             .await
     }
 
-    pub async fn launch_room(&self, player_id: PlayerId, room_id: RoomId) -> Result<GameData> {
-        self.with_data_mut(|server_data| server_data.launch_room(player_id, room_id))
+    pub async fn player_stats(&self, player_id: PlayerId) -> Result<PlayerStats, GameError> {
+        self.with_data(|server_data| server_data.player_stats(player_id))
             .await
     }
 
-    pub async fn get_game_data(&self, player_id: PlayerId, game_id: GameId) -> Result<GameData> {
-        self.with_data(|server_data| server_data.get_game_data(player_id, game_id))
+    pub async fn heartbeat(&self, player_id: PlayerId) -> Result<(), GameError> {
+        self.with_data_mut(|server_data| server_data.heartbeat(player_id))
             .await
     }
 
-    pub async fn play_round(
+    pub async fn take_notifications(
         &self,
         player_id: PlayerId,
-        game_id: GameId,
-        action: ActionKind,
-    ) -> Result<GameData> {
-        self.with_data_mut(|server_data| server_data.play_round(player_id, game_id, action))
+    ) -> Result<Vec<Notification>, GameError> {
+        self.with_data_mut(|server_data| server_data.take_notifications(player_id))
             .await
     }
-}
 
-#[tokio::main]
-async fn main() {
-    let shared_context = Arc::new(ServerContext::new());
-    let thread_server_context = shared_context.clone();
+    pub async fn metrics(&self) -> MetricsResponse {
+        self.with_data(|server_data| MetricsResponse {
+            total_players: server_data.players.len(),
+            active_players: server_data.active_player_count(),
+            rooms: server_data.rooms.len(),
+            games: server_data.games.len(),
+        })
+        .await
+    }
 
-    let app = Router::new()
-        .route("/player/new", get(new_player))
-        .route("/rooms/list", get(rooms_list))
-        .route("/room/new", get(new_room))
-        .route("/room/join", get(join_room))
-        .route("/room/leave", get(leave_room))
-        .route("/room/data", get(get_room_data))
-        .route("/room/launch", get(launch_room))
-        .route("/game/data", get(get_game_data))
-        .route("/game/play", get(play_round))
-        .layer(CorsLayer::permissive())
-        .with_state(shared_context.clone());
+    pub async fn suspicious_players(&self) -> Vec<(PlayerData, MoveStats)> {
+        self.with_data(ServerData::suspicious_players).await
+    }
 
-    let axum_lobby_handle = tokio::spawn(
-        axum::Server::bind(&"0.0.0.0:3000".parse().unwrap()).serve(app.into_make_service()),
-    );
+    pub async fn leaderboard(&self, limit: Option<usize>) -> Vec<(PlayerData, PlayerStats)> {
+        self.with_data(|server_data| {
+            server_data.leaderboard(limit.unwrap_or(server_data.config.leaderboard_size))
+        })
+        .await
+    }
 
-    /*let app = Router::new()
-        .route("/game/data", get(get_game_data))
-        .layer(CorsLayer::permissive())
-        .with_state(shared_context.clone());
+    pub async fn get_rooms_filtered(&self, player_id: Option<PlayerId>) -> Vec<RoomData> {
+        self.with_data_mut(|server_data| server_data.get_rooms_filtered(player_id))
+            .await
+    }
 
-    let axum_rps_handle = tokio::spawn(
-        axum::Server::bind(&"0.0.0.0:3001".parse().unwrap()).serve(app.into_make_service()),
-    );*/
+    pub async fn featured_game(&self) -> Result<GameData, GameError> {
+        self.with_data(|server_data| server_data.featured_game())
+            .await
+    }
 
-    let log_feed_handle = tokio::spawn(async move {
-        loop {
-            {
-                let server_data = thread_server_context.server_data.lock().await;
+    pub async fn get_games_list(&self, include_ended: bool) -> Vec<GameData> {
+        let games = self
+            .with_data(|server_data| server_data.get_games_list())
+            .await;
 
-                println!("Total players count : {}", server_data.players.len());
-                println!("Total rooms count : {}", server_data.rooms.len());
-                println!("Total games count : {}", server_data.games.len());
-            }
-            std::thread::sleep(std::time::Duration::from_millis(5000));
-        }
-    });
+        games
+            .into_iter()
+            .filter(|game| include_ended || game.status != GameStatus::Ended)
+            .collect()
+    }
 
-    let _ = tokio::join!(axum_lobby_handle);
-    //tokio::join!(axum_rps_handle);
-    let _ = tokio::join!(log_feed_handle);
-}
+    pub async fn add_friend(
+        &self,
+        player_id: PlayerId,
+        friend_id: PlayerId,
+    ) -> Result<PlayerData, GameError> {
+        self.with_data_mut(|server_data| server_data.add_friend(player_id, friend_id))
+            .await
+    }
 
-async fn new_player(
-    new_player_query: Option<Query<NewPlayerQuery>>,
-    State(ctx): State<Arc<ServerContext>>,
-) -> Response {
-    let player_name = if let Some(new_player_query) = new_player_query {
-        new_player_query.name.clone()
-    } else {
-        "toto".to_string()
-    };
+    pub async fn remove_friend(
+        &self,
+        player_id: PlayerId,
+        friend_id: PlayerId,
+    ) -> Result<PlayerData, GameError> {
+        self.with_data_mut(|server_data| server_data.remove_friend(player_id, friend_id))
+            .await
+    }
 
-    match ctx.create_player_with_name(player_name).await {
-        Ok(player_data) => Json(NewPlayerResponse::from(player_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    #[cfg(test)]
+    pub async fn create_room(
+        &self,
+        player_id: PlayerId,
+        room_name: String,
+        settings: Option<GameSettings>,
+        visibility: RoomVisibility,
+        password: Option<String>,
+    ) -> Result<RoomData, GameError> {
+        self.create_room_with_bots(
+            player_id,
+            room_name,
+            settings,
+            visibility,
+            password,
+            (0, BotStrategy::default()),
+        )
+        .await
     }
-}
 
-async fn rooms_list(State(ctx): State<Arc<ServerContext>>) -> Response {
-    let rooms_list = ctx.get_rooms_list().await;
-    Json(RoomsListResponse::from(rooms_list)).into_response()
-}
+    //The room isn't created when its bots can't be seated
+    pub async fn create_room_with_bots(
+        &self,
+        player_id: PlayerId,
+        room_name: String,
+        settings: Option<GameSettings>,
+        visibility: RoomVisibility,
+        password: Option<String>,
+        (bot_count, bot_strategy): (usize, BotStrategy),
+    ) -> Result<RoomData, GameError> {
+        self.with_data_mut(|server_data| {
+            let room_data = server_data.create_room(player_id, room_name, settings)?;
+            if let Err(e) = server_data.seat_bots(room_data.id, bot_count, bot_strategy) {
+                server_data.rooms.remove(&room_data.id);
+                return Err(e);
+            }
+            server_data.set_room_visibility(player_id, room_data.id, visibility)?;
+            server_data.set_room_password(player_id, room_data.id, password)
+        })
+        .await
+    }
 
-async fn new_room(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(new_room_query): Query<NewRoomQuery>,
-) -> Response {
-    match ctx
-        .create_room(
-            new_room_query.player_id,
-            new_room_query.room_name,
-            new_room_query.settings,
-        )
+    pub async fn join_room(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        password: Option<String>,
+    ) -> Result<(RoomData, bool), GameError> {
+        self.with_data_mut(|server_data| {
+            let result =
+                server_data.join_room_with_password(player_id, room_id, password.as_deref());
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
         .await
-    {
-        Ok(room_data) => Json(NewRoomResponse::from(room_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
-}
 
-async fn join_room(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(join_room_query): Query<JoinGetLeaveRoomQuery>,
-) -> Response {
-    match ctx
-        .join_room(join_room_query.player_id, join_room_query.room_id)
+    pub async fn join_room_as_spectator(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        password: Option<String>,
+    ) -> Result<RoomData, GameError> {
+        self.with_data_mut(|server_data| {
+            let result =
+                server_data.join_room_as_spectator(player_id, room_id, password.as_deref());
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
         .await
-    {
-        Ok(room_data) => Json(JoinGetRoomResponse::from(room_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
-}
 
-async fn leave_room(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(leave_room_query): Query<JoinGetLeaveRoomQuery>,
-) -> Response {
-    match ctx
-        .leave_room(leave_room_query.player_id, leave_room_query.room_id)
+    pub async fn set_ready(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        ready: bool,
+    ) -> Result<RoomData, GameError> {
+        self.with_data_mut(|server_data| {
+            let result = server_data.set_ready(player_id, room_id, ready);
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
         .await
-    {
-        Ok(_) => (StatusCode::OK, "Ok").into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
-}
 
-async fn get_room_data(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(get_room_data_query): Query<JoinGetLeaveRoomQuery>,
-) -> Response {
-    match ctx
-        .get_room_data(get_room_data_query.player_id, get_room_data_query.room_id)
+    pub async fn leave_room(&self, player_id: PlayerId, room_id: RoomId) -> Result<(), GameError> {
+        self.with_data_mut(|server_data| {
+            let result = server_data.leave_room(player_id, room_id);
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
         .await
-    {
-        Ok(room_data) => Json(JoinGetRoomResponse::from(room_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
-}
 
-async fn launch_room(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(launch_game_query): Query<LaunchGameQuery>,
-) -> Response {
-    match ctx
-        .launch_room(launch_game_query.player_id, launch_game_query.room_id)
+    pub async fn kick_player(
+        &self,
+        host_id: PlayerId,
+        room_id: RoomId,
+        target_id: PlayerId,
+    ) -> Result<RoomData, GameError> {
+        self.with_data_mut(|server_data| {
+            let result = server_data.kick_player(host_id, room_id, target_id);
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
         .await
-    {
-        Ok(game_data) => Json(LaunchGetGameResponse::from(game_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
-}
 
-async fn get_game_data(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(get_game_query): Query<GetGameQuery>,
-) -> Response {
-    match ctx
-        .get_game_data(get_game_query.player_id, get_game_query.game_id)
+    pub async fn authenticate_admin(&self, token: &str) -> Result<(), GameError> {
+        self.with_data(|server_data| server_data.authenticate_admin(token))
+            .await
+    }
+
+    pub async fn merge_rooms(
+        &self,
+        source_room_id: RoomId,
+        target_room_id: RoomId,
+    ) -> Result<RoomData, GameError> {
+        self.with_data_mut(|server_data| server_data.merge_rooms(source_room_id, target_room_id))
+            .await
+    }
+
+    pub async fn get_room_data(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<RoomData, GameError> {
+        self.with_data(|server_data| server_data.get_room_data(player_id, room_id))
+            .await
+    }
+
+    pub async fn games_for_room_history(&self, room_id: RoomId) -> Result<GameId, GameError> {
+        self.with_data(|server_data| server_data.games_for_room_history(room_id))
+            .await
+    }
+
+    pub async fn launch_readiness(&self, player_id: PlayerId, room_id: RoomId) -> LaunchReadiness {
+        self.with_data(|server_data| server_data.launch_readiness(player_id, room_id))
+            .await
+    }
+
+    pub async fn start_replay_practice(
+        &self,
+        player_id: PlayerId,
+        source_game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.with_data_mut(|server_data| {
+            server_data.start_replay_practice(player_id, source_game_id)
+        })
         .await
-    {
-        Ok(game_data) => Json(LaunchGetGameResponse::from(game_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
     }
-}
 
-async fn play_round(
-    State(ctx): State<Arc<ServerContext>>,
-    Query(play_round_query): Query<PlayRoundQuery>,
-) -> Response {
-    match ctx
-        .play_round(
-            play_round_query.player_id,
-            play_round_query.game_id,
-            play_round_query.action,
-        )
+    pub async fn rematch(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.with_data_mut(|server_data| server_data.rematch(player_id, game_id))
+            .await
+    }
+
+    pub async fn reduce_player_count(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<RoomData, GameError> {
+        self.with_data_mut(|server_data| server_data.reduce_player_count(player_id, room_id))
+            .await
+    }
+
+    pub async fn launch_room_with_bots(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        strategy: BotStrategy,
+    ) -> Result<GameData, GameError> {
+        self.with_data_mut(|server_data| {
+            let result = server_data.launch_room_with_bots(player_id, room_id, strategy);
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
         .await
-    {
-        Ok(game_data) => Json(LaunchGetGameResponse::from(game_data)).into_response(),
-        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+
+    pub async fn launch_room(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<GameData, GameError> {
+        self.with_data_mut(|server_data| {
+            let result = server_data.launch_room(player_id, room_id);
+            if result.is_ok() {
+                self.publish_room_update(server_data, room_id);
+            }
+            result
+        })
+        .await
+    }
+
+    pub async fn spectate_game(
+        &self,
+        player_id: Option<PlayerId>,
+        game_id: GameId,
+    ) -> Result<SpectateGameResponse, GameError> {
+        self.with_data_mut(|server_data| {
+            let game_data = match player_id {
+                Some(player_id) => server_data.spectate_game(player_id, game_id)?,
+                None => server_data.get_public_game_data(game_id)?,
+            };
+            Ok(game_data.to_spectator_response(server_data.clock.now()))
+        })
+        .await
+    }
+
+    pub async fn reset_current_round(
+        &self,
+        host_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let result = self
+            .with_data_mut(|server_data| server_data.reset_current_round(host_id, game_id))
+            .await;
+
+        if let Ok(game_data) = &result {
+            self.publish_game_update(game_data);
+        }
+
+        result
+    }
+
+    pub async fn forfeit_game(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let result = self
+            .with_data_mut(|server_data| server_data.forfeit_game(player_id, game_id))
+            .await;
+
+        if let Ok(game_data) = &result {
+            self.publish_game_update(game_data);
+        }
+
+        result
+    }
+
+    pub async fn pause_game(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let result = self
+            .with_data_mut(|server_data| server_data.pause_game(player_id, game_id))
+            .await;
+
+        if let Ok(game_data) = &result {
+            self.publish_game_update(game_data);
+        }
+
+        result
+    }
+
+    pub async fn resume_game(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let result = self
+            .with_data_mut(|server_data| server_data.resume_game(player_id, game_id))
+            .await;
+
+        if let Ok(game_data) = &result {
+            self.publish_game_update(game_data);
+        }
+
+        result
+    }
+
+    pub async fn get_game_data(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.with_data(|server_data| server_data.get_game_data(player_id, game_id))
+            .await
+    }
+
+    pub async fn get_games_batch(
+        &self,
+        player_id: PlayerId,
+        game_ids: Vec<GameId>,
+    ) -> Result<Vec<GameData>, GameError> {
+        self.with_data(|server_data| server_data.get_games_batch(player_id, &game_ids))
+            .await
+    }
+
+    pub async fn get_round(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+        round_index: usize,
+    ) -> Result<RoundData, GameError> {
+        self.with_data(|server_data| server_data.get_round(player_id, game_id, round_index))
+            .await
+    }
+
+    pub async fn get_legal_actions(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<Vec<ActionKind>, GameError> {
+        self.with_data(|server_data| server_data.get_legal_actions(player_id, game_id))
+            .await
+    }
+
+    pub async fn play_round(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+        action: ActionKind,
+        expected_version: Option<u64>,
+        idempotency_key: Option<String>,
+    ) -> Result<GameData, GameError> {
+        let result = self
+            .play_move(
+                player_id,
+                game_id,
+                action,
+                expected_version,
+                idempotency_key.as_deref(),
+            )
+            .await;
+
+        //The server lock is taken again afterwards to count the move, and to
+        //release the players if the move ended the game
+        let result = self
+            .with_data_mut(|server_data| server_data.settle_move(player_id, result))
+            .await;
+
+        if let Ok(game_data) = &result {
+            self.publish_game_update(game_data);
+        }
+
+        result
+    }
+
+    async fn play_move(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+        action: ActionKind,
+        expected_version: Option<u64>,
+        idempotency_key: Option<&str>,
+    ) -> Result<(GameData, bool), GameError> {
+        let (game, context) = self
+            .with_data(|server_data| server_data.prepare_move(player_id, game_id))
+            .await?;
+
+        //Only this game is locked while the move is played, on a blocking
+        //thread as its lock is a std one: a busy game must not hold up an
+        //async worker
+        let idempotency_key = idempotency_key.map(str::to_string);
+        tokio::task::spawn_blocking(move || {
+            ServerData::apply_move(
+                &game,
+                player_id,
+                action,
+                expected_version,
+                idempotency_key.as_deref(),
+                &context,
+            )
+        })
+        .await
+        .unwrap()
+    }
+
+    pub async fn expire_rounds(&self) -> Vec<GameId> {
+        let (expired, games) = self
+            .with_data_mut(|server_data| {
+                let expired = server_data.expire_rounds();
+                let games = expired
+                    .iter()
+                    .filter_map(|game_id| server_data.get_public_game_data(*game_id).ok())
+                    .collect::<Vec<_>>();
+                (expired, games)
+            })
+            .await;
+
+        for game_data in &games {
+            self.publish_game_update(game_data);
+        }
+
+        expired
+    }
+
+    pub async fn forfeit_disconnected_players(&self) -> Vec<GameId> {
+        let (forfeited, games) = self
+            .with_data_mut(|server_data| {
+                let forfeited = server_data.forfeit_disconnected_players();
+                let games = forfeited
+                    .iter()
+                    .filter_map(|game_id| server_data.get_public_game_data(*game_id).ok())
+                    .collect::<Vec<_>>();
+                (forfeited, games)
+            })
+            .await;
+
+        for game_data in &games {
+            self.publish_game_update(game_data);
+        }
+
+        forfeited
+    }
+
+    pub async fn archive_ended_games(&self) -> Vec<GameId> {
+        let archived = self.with_data_mut(ServerData::archive_ended_games).await;
+
+        //Dropping the sender tells the WebSockets following the game that
+        //it is over
+        let mut game_updates = self.game_updates.lock().unwrap();
+        for game_id in &archived {
+            game_updates.remove(game_id);
+        }
+
+        archived
+    }
+
+    pub async fn play_batch(
+        &self,
+        game_id: GameId,
+        moves: Vec<(PlayerId, ActionKind)>,
+    ) -> Result<GameData, GameError> {
+        let (game, context) = self
+            .with_data(|server_data| server_data.prepare_batch(game_id, &moves))
+            .await?;
+
+        //Only this game is locked while the batch is played, like a single
+        //move
+        let (moves, result) = tokio::task::spawn_blocking(move || {
+            let result = ServerData::apply_batch(&game, &moves, &context);
+            (moves, result)
+        })
+        .await
+        .unwrap();
+
+        let result = self
+            .with_data_mut(|server_data| server_data.settle_batch(&moves, result))
+            .await;
+
+        if let Ok(game_data) = &result {
+            self.publish_game_update(game_data);
+        }
+
+        result
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let config = ServerConfig::from_env().unwrap();
+    let bind_addr = config.bind_addr;
+    let shared_context = Arc::new(ServerContext::with_config(config));
+    let thread_server_context = shared_context.clone();
+
+    let app = router(shared_context.clone());
+
+    let axum_lobby_handle =
+        tokio::spawn(axum::Server::bind(&bind_addr).serve(app.into_make_service()));
+
+    #[cfg(feature = "http3")]
+    {
+        let (endpoint, _) = http3::bind(bind_addr).unwrap();
+        tokio::spawn(http3::serve(endpoint, shared_context.clone()));
+    }
+
+    /*let app = Router::new()
+        .route("/game/data", get(get_game_data))
+        .layer(CorsLayer::permissive())
+        .with_state(shared_context.clone());
+
+    let axum_rps_handle = tokio::spawn(
+        axum::Server::bind(&"0.0.0.0:3001".parse().unwrap()).serve(app.into_make_service()),
+    );*/
+
+    let expiry_server_context = shared_context.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            expiry_server_context.expire_rounds().await;
+            expiry_server_context.forfeit_disconnected_players().await;
+            expiry_server_context.archive_ended_games().await;
+        }
+    });
+
+    let log_feed_handle = tokio::spawn(async move {
+        loop {
+            {
+                let server_data = thread_server_context.server_data.lock().await;
+
+                println!("Total players count : {}", server_data.players.len());
+                println!("Total rooms count : {}", server_data.rooms.len());
+                println!("Total games count : {}", server_data.games.len());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5000));
+        }
+    });
+
+    let _ = tokio::join!(axum_lobby_handle);
+    //tokio::join!(axum_rps_handle);
+    let _ = tokio::join!(log_feed_handle);
+}
+
+fn router(ctx: Arc<ServerContext>) -> Router {
+    Router::new()
+        .route("/player/new", post(new_player))
+        .route("/player/new_ephemeral", post(new_ephemeral_player))
+        .route("/player/rename", post(rename_player))
+        .route("/player/friends/add", post(add_friend))
+        .route("/player/friends/remove", post(remove_friend))
+        .route("/rooms/list", get(rooms_list))
+        .route("/leaderboard", get(leaderboard))
+        .route("/player/heartbeat", post(heartbeat))
+        .route("/player/analytics", get(player_analytics))
+        .route("/player/stats", get(player_stats))
+        .route("/player/notifications", post(take_notifications))
+        .route("/lobby/presence", get(presence::presence_socket))
+        .route("/metrics", get(metrics))
+        .route("/matchmaking/join", post(matchmake))
+        .route("/matchmaking/estimate", get(estimate_wait))
+        .route("/room/new", post(new_room))
+        .route("/room/join", post(join_room))
+        .route("/room/spectate", post(spectate_room))
+        .route("/room/leave", post(leave_room))
+        .route("/room/ready", post(set_ready))
+        .route("/room/kick", post(kick_player))
+        .route("/room/data", get(get_room_data))
+        .route("/room/events", get(room_events::room_events))
+        .route("/admin/room/merge", post(merge_rooms))
+        .route("/admin/players/suspicious", get(suspicious_players))
+        .route("/room/launch_status", get(launch_status))
+        .route("/room/game", get(room_game))
+        .route("/room/launch", post(launch_room))
+        .route("/room/launch_with_bots", post(launch_room_with_bots))
+        .route("/room/reduce_count", post(reduce_player_count))
+        .route("/game/list", get(games_list))
+        .route("/game/data", get(get_game_data))
+        .route("/game/ws", get(game_socket::game_socket))
+        .route("/game/round", get(get_round))
+        .route("/game/spectate", post(spectate_game))
+        .route("/spectate/featured", get(featured_game))
+        //The ids don't fit in a query string, they are sent as a JSON body
+        .route("/games/batch", post(get_games_batch))
+        .route("/game/replay_practice", post(start_replay_practice))
+        .route("/game/rematch", post(rematch))
+        .route("/game/legal_actions", get(get_legal_actions))
+        .route("/game/play", post(play_round))
+        .route("/game/reset_round", post(reset_current_round))
+        .route("/game/forfeit", post(forfeit_game))
+        .route("/game/pause", post(pause_game))
+        .route("/game/resume", post(resume_game))
+        .route("/game/play_batch", post(play_batch))
+        .fallback(not_found)
+        .layer(CorsLayer::permissive())
+        .with_state(ctx)
+}
+
+async fn not_found(uri: Uri) -> Response {
+    let error = ErrorResponse {
+        code: GameError::NotFound,
+        message: format!("No route for {}", uri.path()),
+    };
+
+    (StatusCode::NOT_FOUND, Json(error)).into_response()
+}
+
+async fn new_player(
+    State(ctx): State<Arc<ServerContext>>,
+    new_player_query: Option<Json<NewPlayerQuery>>,
+) -> Response {
+    let (player_name, region) = if let Some(Json(new_player_query)) = new_player_query {
+        (new_player_query.name, new_player_query.region)
+    } else {
+        ("toto".to_string(), None)
+    };
+
+    let player_data = match ctx.create_player_with_region(player_name, region).await {
+        Ok(player_data) => player_data,
+        Err(e) => return ApiError::from(e).into_response(),
+    };
+
+    match ctx.session_token(player_data.id).await {
+        Ok(token) => Json(NewPlayerResponse::new(player_data, token)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn rename_player(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(rename_query): ApiJson<RenamePlayerQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(rename_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .rename_player(rename_query.player_id, rename_query.name)
+        .await
+    {
+        Ok(player_data) => Json(PlayerFullData::from(player_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn matchmake(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(matchmaking_query): ApiJson<MatchmakingQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(matchmaking_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx.matchmake(matchmaking_query.player_id).await {
+        Ok(game_data) => Json(MatchmakingResponse::from(game_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn estimate_wait(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(matchmaking_query): ApiQuery<MatchmakingQuery>,
+) -> Response {
+    match ctx.estimate_wait(matchmaking_query.player_id).await {
+        Ok(estimated_wait_ms) => {
+            Json(MatchmakingEstimateResponse { estimated_wait_ms }).into_response()
+        }
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn new_ephemeral_player(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiJson(new_player_query): ApiJson<NewPlayerQuery>,
+) -> Response {
+    let player_data = match ctx.create_ephemeral_player(new_player_query.name).await {
+        Ok(player_data) => player_data,
+        Err(e) => return ApiError::from(e).into_response(),
+    };
+
+    match ctx.session_token(player_data.id).await {
+        Ok(token) => Json(NewPlayerResponse::new(player_data, token)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn heartbeat(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(heartbeat_query): ApiJson<HeartbeatQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(heartbeat_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx.heartbeat(heartbeat_query.player_id).await {
+        Ok(_) => (StatusCode::OK, "Ok").into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn player_analytics(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(analytics_query): ApiQuery<AnalyticsQuery>,
+) -> Response {
+    match ctx.player_analytics(analytics_query.player_id).await {
+        Ok(analytics) => Json(analytics).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn player_stats(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(stats_query): ApiQuery<PlayerStatsQuery>,
+) -> Response {
+    match ctx.player_stats(stats_query.player_id).await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn take_notifications(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(notifications_query): ApiJson<NotificationsQuery>,
+) -> Response {
+    if let Err(e) = ctx
+        .authenticate(notifications_query.player_id, &token)
+        .await
+    {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx.take_notifications(notifications_query.player_id).await {
+        Ok(notifications) => Json(NotificationsResponse { notifications }).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn metrics(State(ctx): State<Arc<ServerContext>>) -> Response {
+    Json(ctx.metrics().await).into_response()
+}
+
+async fn suspicious_players(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+) -> Response {
+    if let Err(e) = ctx.authenticate_admin(&token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    let players = ctx.suspicious_players().await;
+    Json(SuspiciousPlayersResponse::from(players)).into_response()
+}
+
+async fn leaderboard(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(leaderboard_query): ApiQuery<LeaderboardQuery>,
+) -> Response {
+    let leaderboard = ctx.leaderboard(leaderboard_query.limit).await;
+    Json(LeaderboardResponse::from(leaderboard)).into_response()
+}
+
+async fn add_friend(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(friend_query): ApiJson<FriendQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(friend_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .add_friend(friend_query.player_id, friend_query.friend_id)
+        .await
+    {
+        Ok(player_data) => Json(FriendsResponse::from(player_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn remove_friend(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(friend_query): ApiJson<FriendQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(friend_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .remove_friend(friend_query.player_id, friend_query.friend_id)
+        .await
+    {
+        Ok(player_data) => Json(FriendsResponse::from(player_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn rooms_list(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(rooms_list_query): ApiQuery<RoomsListQuery>,
+) -> Response {
+    let rooms_list = ctx.get_rooms_filtered(rooms_list_query.player_id).await;
+    Json(RoomsListResponse::from(rooms_list)).into_response()
+}
+
+async fn games_list(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(games_list_query): ApiQuery<GamesListQuery>,
+) -> Response {
+    let games_list = ctx.get_games_list(games_list_query.include_ended).await;
+    Json(GamesListResponse::from(games_list)).into_response()
+}
+
+async fn new_room(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(new_room_query): ApiJson<NewRoomQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(new_room_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    let settings = new_room_query.game_settings();
+    match ctx
+        .create_room_with_bots(
+            new_room_query.player_id,
+            new_room_query.room_name,
+            Some(settings),
+            new_room_query.visibility,
+            new_room_query.password,
+            (new_room_query.bot_count, new_room_query.bot_strategy),
+        )
+        .await
+    {
+        Ok(room_data) => Json(NewRoomResponse::from(room_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn join_room(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(join_room_query): ApiJson<JoinGetLeaveRoomQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(join_room_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .join_room(
+            join_room_query.player_id,
+            join_room_query.room_id,
+            join_room_query.password,
+        )
+        .await
+    {
+        Ok((room_data, became_full)) => Json(JoinGetRoomResponse::for_player(
+            room_data,
+            became_full,
+            join_room_query.player_id,
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn spectate_room(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(spectate_room_query): ApiJson<JoinGetLeaveRoomQuery>,
+) -> Response {
+    if let Err(e) = ctx
+        .authenticate(spectate_room_query.player_id, &token)
+        .await
+    {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .join_room_as_spectator(
+            spectate_room_query.player_id,
+            spectate_room_query.room_id,
+            spectate_room_query.password,
+        )
+        .await
+    {
+        Ok(room_data) => Json(JoinGetRoomResponse::for_player(
+            room_data,
+            false,
+            spectate_room_query.player_id,
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn set_ready(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(ready_query): ApiJson<ReadyQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(ready_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .set_ready(
+            ready_query.player_id,
+            ready_query.room_id,
+            ready_query.ready,
+        )
+        .await
+    {
+        Ok(room_data) => Json(JoinGetRoomResponse::for_player(
+            room_data,
+            false,
+            ready_query.player_id,
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn leave_room(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(leave_room_query): ApiJson<JoinGetLeaveRoomQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(leave_room_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .leave_room(leave_room_query.player_id, leave_room_query.room_id)
+        .await
+    {
+        Ok(_) => (StatusCode::OK, "Ok").into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn kick_player(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(kick_query): ApiJson<KickPlayerQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(kick_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .kick_player(
+            kick_query.player_id,
+            kick_query.room_id,
+            kick_query.target_id,
+        )
+        .await
+    {
+        Ok(room_data) => Json(JoinGetRoomResponse::for_player(
+            room_data,
+            false,
+            kick_query.player_id,
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn merge_rooms(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(merge_rooms_query): ApiJson<MergeRoomsQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate_admin(&token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .merge_rooms(
+            merge_rooms_query.source_room_id,
+            merge_rooms_query.target_room_id,
+        )
+        .await
+    {
+        Ok(room_data) => Json(JoinGetRoomResponse::from(room_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn get_room_data(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(get_room_data_query): ApiQuery<JoinGetLeaveRoomQuery>,
+) -> Response {
+    match ctx
+        .get_room_data(get_room_data_query.player_id, get_room_data_query.room_id)
+        .await
+    {
+        Ok(room_data) => Json(JoinGetRoomResponse::for_player(
+            room_data,
+            false,
+            get_room_data_query.player_id,
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn room_game(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(room_game_query): ApiQuery<RoomGameQuery>,
+) -> Response {
+    match ctx.games_for_room_history(room_game_query.room_id).await {
+        Ok(game_id) => Json(RoomGameResponse { game_id }).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn launch_status(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(launch_game_query): ApiQuery<LaunchGameQuery>,
+) -> Response {
+    let readiness = ctx
+        .launch_readiness(launch_game_query.player_id, launch_game_query.room_id)
+        .await;
+    Json(LaunchStatusResponse::from(readiness)).into_response()
+}
+
+async fn launch_room(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(launch_game_query): ApiJson<LaunchGameQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(launch_game_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .launch_room(launch_game_query.player_id, launch_game_query.room_id)
+        .await
+    {
+        Ok(game_data) => Json(LaunchGetGameResponse::for_player(
+            game_data,
+            Some(launch_game_query.player_id),
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+fn game_etag(game_data: &GameData) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", game_data.version)).unwrap()
+}
+
+async fn reduce_player_count(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(room_query): ApiJson<JoinGetLeaveRoomQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(room_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .reduce_player_count(room_query.player_id, room_query.room_id)
+        .await
+    {
+        Ok(room_data) => Json(JoinGetRoomResponse::for_player(
+            room_data,
+            false,
+            room_query.player_id,
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn launch_room_with_bots(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(launch_query): ApiJson<LaunchWithBotsQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(launch_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .launch_room_with_bots(
+            launch_query.player_id,
+            launch_query.room_id,
+            launch_query.strategy,
+        )
+        .await
+    {
+        Ok(game_data) => Json(LaunchGetGameResponse::for_player(
+            game_data,
+            Some(launch_query.player_id),
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn start_replay_practice(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(replay_practice_query): ApiJson<ReplayPracticeQuery>,
+) -> Response {
+    if let Err(e) = ctx
+        .authenticate(replay_practice_query.player_id, &token)
+        .await
+    {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .start_replay_practice(
+            replay_practice_query.player_id,
+            replay_practice_query.source_game_id,
+        )
+        .await
+    {
+        Ok(game_data) => Json(LaunchGetGameResponse::for_player(
+            game_data,
+            Some(replay_practice_query.player_id),
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn rematch(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(rematch_query): ApiJson<RematchQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(rematch_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .rematch(rematch_query.player_id, rematch_query.game_id)
+        .await
+    {
+        Ok(game_data) => Json(LaunchGetGameResponse::for_player(
+            game_data,
+            Some(rematch_query.player_id),
+        ))
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn get_game_data(
+    State(ctx): State<Arc<ServerContext>>,
+    token: Option<SessionToken>,
+    ApiQuery(get_game_query): ApiQuery<GetGameQuery>,
+    headers: HeaderMap,
+) -> Response {
+    //Players only get their own action back with their token, without it
+    //they get the view anyone watching the game gets
+    let authenticated = match token {
+        Some(SessionToken(token)) => {
+            if let Err(e) = ctx.authenticate(get_game_query.player_id, &token).await {
+                return ApiError::from(e).into_response();
+            }
+            true
+        }
+        None => false,
+    };
+
+    match ctx
+        .get_game_data(get_game_query.player_id, get_game_query.game_id)
+        .await
+    {
+        Ok(game_data) => {
+            let etag = game_etag(&game_data);
+            //Pollers sending back the ETag they got are only told nothing changed
+            if headers.get(header::IF_NONE_MATCH) == Some(&etag) {
+                return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+            }
+
+            let player_id = authenticated.then_some(get_game_query.player_id);
+            (
+                [(header::ETAG, etag)],
+                Json(
+                    LaunchGetGameResponse::for_player(game_data, player_id)
+                        .with_scores_map(get_game_query.scores_as_map),
+                ),
+            )
+                .into_response()
+        }
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn get_round(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiQuery(get_round_query): ApiQuery<GetRoundQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(get_round_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .get_round(
+            get_round_query.player_id,
+            get_round_query.game_id,
+            get_round_query.round_index,
+        )
+        .await
+    {
+        Ok(round_data) => Json(round_data).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn featured_game(State(ctx): State<Arc<ServerContext>>) -> Response {
+    match ctx.featured_game().await {
+        Ok(game_data) => Json(GamePublicData::from(game_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn get_games_batch(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(games_batch_query): ApiJson<GamesBatchQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(games_batch_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .get_games_batch(games_batch_query.player_id, games_batch_query.game_ids)
+        .await
+    {
+        Ok(games) => Json(GamesBatchResponse {
+            games: games.into_iter().map(GameSummary::from).collect(),
+        })
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn spectate_game(
+    State(ctx): State<Arc<ServerContext>>,
+    token: Option<SessionToken>,
+    ApiJson(spectate_query): ApiJson<SpectateGameQuery>,
+) -> Response {
+    //Anonymous spectators don't need a token
+    if let Some(player_id) = spectate_query.player_id {
+        let token = token.map(|SessionToken(token)| token).unwrap_or_default();
+        if let Err(e) = ctx.authenticate(player_id, &token).await {
+            return ApiError::from(e).into_response();
+        }
+    }
+
+    match ctx
+        .spectate_game(spectate_query.player_id, spectate_query.game_id)
+        .await
+    {
+        Ok(spectate_response) => Json(spectate_response).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn reset_current_round(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(get_game_query): ApiJson<GetGameQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(get_game_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .reset_current_round(get_game_query.player_id, get_game_query.game_id)
+        .await
+    {
+        Ok(game_data) => Json(
+            LaunchGetGameResponse::for_player(game_data, Some(get_game_query.player_id))
+                .with_scores_map(get_game_query.scores_as_map),
+        )
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn forfeit_game(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(get_game_query): ApiJson<GetGameQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(get_game_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .forfeit_game(get_game_query.player_id, get_game_query.game_id)
+        .await
+    {
+        Ok(game_data) => Json(
+            LaunchGetGameResponse::for_player(game_data, Some(get_game_query.player_id))
+                .with_scores_map(get_game_query.scores_as_map),
+        )
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn pause_game(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(get_game_query): ApiJson<GetGameQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(get_game_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .pause_game(get_game_query.player_id, get_game_query.game_id)
+        .await
+    {
+        Ok(game_data) => Json(
+            LaunchGetGameResponse::for_player(game_data, Some(get_game_query.player_id))
+                .with_scores_map(get_game_query.scores_as_map),
+        )
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn resume_game(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(get_game_query): ApiJson<GetGameQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(get_game_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .resume_game(get_game_query.player_id, get_game_query.game_id)
+        .await
+    {
+        Ok(game_data) => Json(
+            LaunchGetGameResponse::for_player(game_data, Some(get_game_query.player_id))
+                .with_scores_map(get_game_query.scores_as_map),
+        )
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn get_legal_actions(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiQuery(get_game_query): ApiQuery<GetGameQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(get_game_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .get_legal_actions(get_game_query.player_id, get_game_query.game_id)
+        .await
+    {
+        Ok(actions) => Json(LegalActionsResponse::from(actions)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn play_round(
+    State(ctx): State<Arc<ServerContext>>,
+    SessionToken(token): SessionToken,
+    ApiJson(play_round_query): ApiJson<PlayRoundQuery>,
+) -> Response {
+    if let Err(e) = ctx.authenticate(play_round_query.player_id, &token).await {
+        return ApiError::from(e).into_response();
+    }
+
+    match ctx
+        .play_round(
+            play_round_query.player_id,
+            play_round_query.game_id,
+            play_round_query.action,
+            play_round_query.expected_version,
+            play_round_query.idempotency_key,
+        )
+        .await
+    {
+        Ok(game_data) => Json(
+            LaunchGetGameResponse::for_player(game_data, Some(play_round_query.player_id))
+                .with_scores_map(play_round_query.scores_as_map),
+        )
+        .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+async fn play_batch(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiJson(play_batch_query): ApiJson<PlayBatchQuery>,
+) -> Response {
+    for player_move in &play_batch_query.moves {
+        if let Err(e) = ctx
+            .authenticate(player_move.player_id, &player_move.token)
+            .await
+        {
+            return ApiError::from(e).into_response();
+        }
+    }
+
+    match ctx
+        .play_batch(
+            play_batch_query.game_id,
+            play_batch_query
+                .moves
+                .into_iter()
+                .map(|player_move| (player_move.player_id, player_move.action))
+                .collect(),
+        )
+        .await
+    {
+        Ok(game_data) => Json(LaunchGetGameResponse::from(game_data)).into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tower::ServiceExt;
+
+    use super::*;
+
+    //A JSON POST, authenticated when a session token is given
+    fn post_json(
+        path: &str,
+        token: Option<&str>,
+        body: serde_json::Value,
+    ) -> axum::http::Request<axum::body::Body> {
+        let mut request = axum::http::Request::post(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        if let Some(token) = token {
+            request.headers_mut().insert(
+                header::AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+            );
+        }
+        request
+    }
+
+    async fn send(
+        app: &Router,
+        request: axum::http::Request<axum::body::Body>,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    //Plays a whole game over HTTP only, the way a client does
+    #[tokio::test]
+    async fn test_http_game_flow() {
+        let app = router(Arc::new(ServerContext::new()));
+
+        let mut players = vec![];
+        for name in ["Alice", "Bob", "Carol"] {
+            let (status, body) = send(
+                &app,
+                post_json("/player/new", None, serde_json::json!({ "name": name })),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["player"]["name"], name);
+            players.push((
+                body["player"]["id"].clone(),
+                body["token"].as_str().unwrap().to_string(),
+            ));
+        }
+        let [(alice, alice_token), (bob, bob_token), (carol, carol_token)] =
+            <[_; 3]>::try_from(players).unwrap();
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/new",
+                Some(&alice_token),
+                serde_json::json!({ "player_id": alice, "room_name": "test room" }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["room"]["players"][0]["name"], "Alice");
+        let room_id = body["room"]["id"].clone();
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/join",
+                Some(&bob_token),
+                serde_json::json!({ "player_id": bob, "room_id": room_id }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["became_full"], true);
+        assert_eq!(body["is_host"], false);
+
+        //The room only has two seats
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/join",
+                Some(&carol_token),
+                serde_json::json!({ "player_id": carol, "room_id": room_id }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "ROOM_FULL");
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/join",
+                Some(&carol_token),
+                serde_json::json!({ "player_id": carol, "room_id": 1234 }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "UNKNOWN_ROOM");
+
+        let launch = || {
+            post_json(
+                "/room/launch",
+                Some(&alice_token),
+                serde_json::json!({ "player_id": alice, "room_id": room_id }),
+            )
+        };
+
+        //Bob has to be ready first
+        let (status, body) = send(&app, launch()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "PLAYERS_NOT_READY");
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/ready",
+                Some(&bob_token),
+                serde_json::json!({ "player_id": bob, "room_id": room_id, "ready": true }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["room"]["ready"][0]["name"], "Bob");
+
+        let (status, body) = send(&app, launch()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Running");
+        let game_id = body["id"].clone();
+
+        let play = |player_id: &serde_json::Value, token: &str, action: &str| {
+            post_json(
+                "/game/play",
+                Some(token),
+                serde_json::json!({ "player_id": player_id, "game_id": game_id, "action": action }),
+            )
+        };
+        for _ in 0..3 {
+            let (status, body) = send(&app, play(&alice, &alice_token, "Rock")).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["your_current_action"], "Rock");
+            let (status, _) = send(&app, play(&bob, &bob_token, "Scissors")).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let (status, body) = send(
+            &app,
+            axum::http::Request::get(format!("/game/data?player_id={alice}&game_id={game_id}"))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Ended");
+        assert_eq!(body["winner"]["name"], "Alice");
+        assert_eq!(body["round_history"].as_array().unwrap().len(), 3);
+        assert!(body["round_history"][2]["resolved_at"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_http_game_against_a_bot() {
+        let app = router(Arc::new(ServerContext::new()));
+
+        let (_, body) = send(
+            &app,
+            post_json("/player/new", None, serde_json::json!({ "name": "Alice" })),
+        )
+        .await;
+        let alice = body["player"]["id"].clone();
+        let alice_token = body["token"].as_str().unwrap().to_string();
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/new",
+                Some(&alice_token),
+                serde_json::json!({
+                    "player_id": alice,
+                    "room_name": "practice",
+                    "bot_count": 1,
+                    "bot_strategy": "AlwaysRock",
+                }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["room"]["players"].as_array().unwrap().len(), 2);
+        let room_id = body["room"]["id"].clone();
+
+        //The host is the only seat bots can't take
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/new",
+                Some(&alice_token),
+                serde_json::json!({ "player_id": alice, "room_name": "bots only", "bot_count": 2 }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "NOT_ENOUGH_SEATS");
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/launch",
+                Some(&alice_token),
+                serde_json::json!({ "player_id": alice, "room_id": room_id }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let game_id = body["id"].clone();
+
+        for _ in 0..3 {
+            let (status, _) = send(
+                &app,
+                post_json(
+                    "/game/play",
+                    Some(&alice_token),
+                    serde_json::json!({ "player_id": alice, "game_id": game_id, "action": "Paper" }),
+                ),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let (status, body) = send(
+            &app,
+            axum::http::Request::get(format!("/game/data?player_id={alice}&game_id={game_id}"))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Ended");
+        assert_eq!(body["winner"]["name"], "Alice");
+        assert_eq!(body["round_history"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_host_leaving_hands_the_room_over() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+
+        let room_data_request = || {
+            axum::http::Request::get(format!(
+                "/room/data?player_id={}&room_id={}",
+                bob.id, room_data.id
+            ))
+            .body(axum::body::Body::empty())
+            .unwrap()
+        };
+        let (status, body) = send(&app, room_data_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["room"]["host"]["name"], "Alice");
+        assert_eq!(body["is_host"], false);
+
+        ctx.leave_room(alice.id, room_data.id).await.unwrap();
+        let (status, body) = send(&app, room_data_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["room"]["host"]["name"], "Bob");
+        assert_eq!(body["is_host"], true);
+    }
+
+    #[tokio::test]
+    async fn test_paused_game_refuses_moves() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let bob_token = ctx.session_token(bob.id).await.unwrap();
+        let game = serde_json::json!({ "player_id": bob.id, "game_id": game_data.id });
+        let play = || {
+            post_json(
+                "/game/play",
+                Some(&alice_token),
+                serde_json::json!({
+                    "player_id": alice.id,
+                    "game_id": game_data.id,
+                    "action": "Rock",
+                }),
+            )
+        };
+
+        let (status, body) = send(
+            &app,
+            post_json("/game/pause", Some(&bob_token), game.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Paused");
+
+        let (status, body) = send(&app, play()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "GAME_PAUSED");
+
+        let (status, body) = send(&app, post_json("/game/resume", Some(&bob_token), game)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "Running");
+
+        let (status, _) = send(&app, play()).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_json() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+
+        //A body missing a field, then one which isn't JSON at all
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/join",
+                Some(&alice_token),
+                serde_json::json!({ "player_id": alice.id }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "INVALID_REQUEST");
+        assert!(body["message"].as_str().unwrap().contains("room_id"));
+
+        let mut request = post_json("/room/join", Some(&alice_token), serde_json::json!({}));
+        *request.body_mut() = axum::body::Body::from("{ not json");
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "INVALID_REQUEST");
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get("/game/data?player_id=alice&game_id=0")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, GameError::InvalidRequest);
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/join",
+                Some("not a token"),
+                serde_json::json!({ "player_id": alice.id, "room_id": 0 }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "INVALID_TOKEN");
+
+        let (status, body) = send(
+            &app,
+            post_json(
+                "/room/join",
+                Some(&alice_token),
+                serde_json::json!({ "player_id": alice.id, "room_id": 0 }),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "UNKNOWN_ROOM");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_returns_json() {
+        let response = router(Arc::new(ServerContext::new()))
+            .oneshot(
+                axum::http::Request::get("/does/not/exist")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, GameError::NotFound);
+        assert_eq!(error.message, "No route for /does/not/exist");
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_need_the_admin_token() {
+        let suspicious = |token: Option<&str>| {
+            let request = axum::http::Request::get("/admin/players/suspicious");
+            match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+                None => request,
+            }
+            .body(axum::body::Body::empty())
+            .unwrap()
+        };
+        let merge = |token: Option<&str>| {
+            post_json(
+                "/admin/room/merge",
+                token,
+                serde_json::json!({ "source_room_id": 0, "target_room_id": 1 }),
+            )
+        };
+
+        //Closed to everyone while no admin token is configured
+        let app = router(Arc::new(ServerContext::new()));
+        let (status, _) = send(&app, suspicious(Some(""))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, merge(Some(""))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let app = router(Arc::new(ServerContext::with_config(ServerConfig {
+            admin_token: Some("admin secret".to_string()),
+            ..ServerConfig::default()
+        })));
+        for token in [None, Some("wrong")] {
+            let (status, body) = send(&app, suspicious(token)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(body["code"], "INVALID_TOKEN");
+            let (status, _) = send(&app, merge(token)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+
+        let (status, _) = send(&app, suspicious(Some("admin secret"))).await;
+        assert_eq!(status, StatusCode::OK);
+        //Past the token, the rooms just don't exist
+        let (status, _) = send(&app, merge(Some("admin secret"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_moves_are_counted() {
+        let ctx = Arc::new(ServerContext::with_config(ServerConfig {
+            admin_token: Some("admin secret".to_string()),
+            ..ServerConfig::default()
+        }));
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        ctx.play_round(bob.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+        //Spock is not part of plain Rock Paper Scissors
+        for _ in 0..10 {
+            assert_eq!(
+                ctx.play_round(alice.id, game_data.id, ActionKind::Spock, None, None)
+                    .await
+                    .unwrap_err(),
+                GameError::ActionNotAvailable
+            );
+        }
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/admin/players/suspicious")
+                    .header(header::AUTHORIZATION, "Bearer admin secret")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let suspicious: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let players = suspicious["players"].as_array().unwrap();
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0][0]["name"], "Alice");
+        assert_eq!(players[0][1]["rejected"], 10);
+        assert_eq!(players[0][1]["accepted"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_room_game_link() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let room_game = |room_id: RoomId| {
+            axum::http::Request::get(format!("/room/game?room_id={room_id}"))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(room_game(room_data.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let response = app.oneshot(room_game(room_data.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["game_id"], game_data.id);
+    }
+
+    #[tokio::test]
+    async fn test_games_list() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let games_list = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        axum::http::Request::get(uri)
+                            .body(axum::body::Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()["games"].clone()
+            }
+        };
+
+        let games = games_list("/game/list").await;
+        assert_eq!(games.as_array().unwrap().len(), 1);
+        assert_eq!(games[0]["id"], game_data.id);
+        assert_eq!(games[0]["status"], "Running");
+        assert_eq!(games[0]["players"][0]["name"], "Alice");
+        assert!(games[0].get("current_round").is_none());
+
+        for _ in 0..3 {
+            ctx.play_batch(
+                game_data.id,
+                vec![(alice.id, ActionKind::Rock), (bob.id, ActionKind::Scissors)],
+            )
+            .await
+            .unwrap();
+        }
+
+        assert!(games_list("/game/list")
+            .await
+            .as_array()
+            .unwrap()
+            .is_empty());
+        let games = games_list("/game/list?include_ended=true").await;
+        assert_eq!(games[0]["status"], "Ended");
+    }
+
+    #[tokio::test]
+    async fn test_resubmission_after_reconnect() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+        let first_version = game_data.version;
+
+        ctx.play_round(
+            alice.id,
+            game_data.id,
+            ActionKind::Rock,
+            Some(first_version),
+            Some("alice-1".to_string()),
+        )
+        .await
+        .unwrap();
+        //Bob resolves the round while Alice is disconnected
+        let resolved = ctx
+            .play_round(bob.id, game_data.id, ActionKind::Scissors, None, None)
+            .await
+            .unwrap();
+
+        //Not knowing whether her action went through, Alice sends it again
+        //once reconnected. It isn't played in the next round
+        let game_data = ctx
+            .play_round(
+                alice.id,
+                game_data.id,
+                ActionKind::Rock,
+                Some(first_version),
+                Some("alice-1".to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(game_data.version, resolved.version);
+        assert_eq!(game_data.round_history.len(), 1);
+        assert!(game_data.current_round.inputs.is_empty());
+        assert_eq!(game_data.players[0].1, 1);
+
+        let game_data = ctx
+            .play_round(
+                alice.id,
+                game_data.id,
+                ActionKind::Rock,
+                Some(game_data.version),
+                Some("alice-2".to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            game_data.current_round.inputs.get(&alice.id),
+            Some(&ActionKind::Rock)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resubmitted_winning_move_counted_once() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    end_condition: types::EndCondition::FirstToScore(1),
+                    ..GameSettings::default()
+                }),
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        ctx.play_round(alice.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+        let game_data = ctx
+            .play_round(
+                bob.id,
+                game_data.id,
+                ActionKind::Scissors,
+                None,
+                Some("bob-1".to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+
+        //Bob sends his last move again, the game it ended isn't counted twice
+        ctx.play_round(
+            bob.id,
+            game_data.id,
+            ActionKind::Scissors,
+            None,
+            Some("bob-1".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            ctx.player_stats(alice.id).await.unwrap(),
+            PlayerStats {
+                wins: 1,
+                losses: 0,
+                draws: 0,
+                games_played: 1,
+            }
+        );
+        assert_eq!(ctx.player_stats(bob.id).await.unwrap().games_played, 1);
+    }
+
+    #[tokio::test]
+    async fn test_room_password() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                Some("hunter2".to_string()),
+            )
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/rooms/list")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("hunter2"));
+        let rooms: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rooms["rooms"][0]["has_password"], true);
+
+        for password in [None, Some("hunter3".to_string())] {
+            assert_eq!(
+                ctx.join_room(bob.id, room_data.id, password).await,
+                Err(GameError::WrongPassword)
+            );
+        }
+        let (room_data, _) = ctx
+            .join_room(bob.id, room_data.id, Some("hunter2".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(room_data.players.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_games_batch_needs_session_token() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let bob_token = ctx.session_token(bob.id).await.unwrap();
+        let batch = |token: Option<&str>| {
+            post_json(
+                "/games/batch",
+                token,
+                serde_json::json!({ "player_id": alice.id, "game_ids": [game_data.id] }),
+            )
+        };
+
+        //Nobody gets Alice's games without her token
+        let (status, body) = send(&app, batch(None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "INVALID_TOKEN");
+        let (status, _) = send(&app, batch(Some(&bob_token))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&app, batch(Some(&alice_token))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["games"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_play_needs_session_token() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let play = |token: &str| {
+            axum::http::Request::post("/game/play")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(axum::body::Body::from(
+                    serde_json::json!({
+                        "player_id": alice.id,
+                        "game_id": game_data.id,
+                        "action": "Rock",
+                    })
+                    .to_string(),
+                ))
+                .unwrap()
+        };
+
+        //Bob's token doesn't let anyone play as Alice
+        let bob_token = ctx.session_token(bob.id).await.unwrap();
+        let response = app.clone().oneshot(play(&bob_token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/game/play")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(axum::body::Body::from(
+                        serde_json::json!({
+                            "player_id": alice.id,
+                            "game_id": game_data.id,
+                            "action": "Rock",
+                        })
+                        .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let response = app.oneshot(play(&alice_token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_mutating_routes_need_post() {
+        let app = router(Arc::new(ServerContext::new()));
+
+        let post = |path: &str, body: serde_json::Value| {
+            axum::http::Request::post(path)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(post("/player/new", serde_json::json!({ "name": "Alice" })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let alice: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get(format!(
+                    "/room/new?player_id={}&room_name=test",
+                    alice["player"]["id"]
+                ))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let mut request = post(
+            "/room/new",
+            serde_json::json!({ "player_id": alice["player"]["id"], "room_name": "test" }),
+        );
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", alice["token"].as_str().unwrap())).unwrap(),
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/rooms/list")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let rooms: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rooms["rooms"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_game_data_etag() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let poll = |etag: Option<HeaderValue>| {
+            let mut headers = HeaderMap::new();
+            if let Some(etag) = etag {
+                headers.insert(header::IF_NONE_MATCH, etag);
+            }
+            get_game_data(
+                State(ctx.clone()),
+                None,
+                ApiQuery(GetGameQuery {
+                    player_id: alice.id,
+                    game_id: game_data.id,
+                    scores_as_map: false,
+                }),
+                headers,
+            )
+        };
+
+        let response = poll(None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+
+        let response = poll(Some(etag.clone())).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        ctx.play_round(alice.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+
+        let response = poll(Some(etag.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn test_get_round() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        for _ in 0..3 {
+            ctx.play_round(alice.id, game_data.id, ActionKind::Rock, None, None)
+                .await
+                .unwrap();
+            ctx.play_round(bob.id, game_data.id, ActionKind::Scissors, None, None)
+                .await
+                .unwrap();
+        }
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let get_round = |round_index: usize| {
+            router(ctx.clone()).oneshot(
+                axum::http::Request::get(format!(
+                    "/game/round?player_id={}&game_id={}&round_index={round_index}",
+                    alice.id, game_data.id
+                ))
+                .header(header::AUTHORIZATION, format!("Bearer {alice_token}"))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+            )
+        };
+
+        let response = get_round(0).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let round: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(round["inputs"][alice.id.to_string()], "Rock");
+
+        let response = get_round(3).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, GameError::UnknownRound);
+    }
+
+    #[tokio::test]
+    async fn test_game_data_hides_the_action_without_the_token() {
+        let ctx = Arc::new(ServerContext::new());
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+        ctx.play_round(alice.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let bob_token = ctx.session_token(bob.id).await.unwrap();
+        let game_data_request = |token: Option<&str>| {
+            let request = axum::http::Request::get(format!(
+                "/game/data?player_id={}&game_id={}",
+                alice.id, game_data.id
+            ));
+            match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+                None => request,
+            }
+            .body(axum::body::Body::empty())
+            .unwrap()
+        };
+
+        //Bob asking with Alice's id doesn't get to see her move
+        let (status, body) = send(&app, game_data_request(None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["your_current_action"].is_null());
+
+        let (status, body) = send(&app, game_data_request(Some(&bob_token))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "INVALID_TOKEN");
+
+        let (status, body) = send(&app, game_data_request(Some(&alice_token))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["your_current_action"], "Rock");
+
+        let (status, _) = send(
+            &app,
+            axum::http::Request::get(format!(
+                "/game/legal_actions?player_id={}&game_id={}",
+                alice.id, game_data.id
+            ))
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    //Polling the game with their token keeps players from being forfeited,
+    //polling it with only their id doesn't
+    #[tokio::test]
+    async fn test_polling_players_are_not_forfeited() {
+        let ctx = Arc::new(ServerContext::with_config(ServerConfig {
+            disconnect_after_ms: 1000,
+            ..ServerConfig::default()
+        }));
+        ctx.server_data.lock().await.clock = clock::Clock::Manual(0);
+        let app = router(ctx.clone());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let poll = |player_id: PlayerId, token: Option<&str>| {
+            let request = axum::http::Request::get(format!(
+                "/game/data?player_id={player_id}&game_id={}",
+                game_data.id
+            ));
+            match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+                None => request,
+            }
+            .body(axum::body::Body::empty())
+            .unwrap()
+        };
+
+        for _ in 0..3 {
+            ctx.server_data.lock().await.clock.advance(600);
+            let (status, _) = send(&app, poll(alice.id, Some(&alice_token))).await;
+            assert_eq!(status, StatusCode::OK);
+            let (status, _) = send(&app, poll(bob.id, None)).await;
+            assert_eq!(status, StatusCode::OK);
+            ctx.forfeit_disconnected_players().await;
+        }
+
+        let game_data = ctx.get_game_data(alice.id, game_data.id).await.unwrap();
+        assert_eq!(game_data.forfeited, vec![bob.id]);
+        assert_eq!(game_data.winner, Some(alice.id));
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_spectator() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let spectate = || async {
+            let response = router(ctx.clone())
+                .oneshot(
+                    axum::http::Request::post("/game/spectate")
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .body(axum::body::Body::from(
+                            serde_json::json!({ "game_id": game_data.id }).to_string(),
+                        ))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        //alice's action stays hidden while bob hasn't played
+        ctx.play_round(alice.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+        let game = spectate().await;
+        assert_eq!(game["round_history"], serde_json::json!([]));
+        assert_eq!(game["delayed_actions"], serde_json::json!({}));
+        assert!(game.get("current_round").is_none());
+        assert_eq!(game["observers"], serde_json::json!([]));
+
+        //and shows up once the round is resolved
+        ctx.play_round(bob.id, game_data.id, ActionKind::Scissors, None, None)
+            .await
+            .unwrap();
+        let game = spectate().await;
+        assert_eq!(
+            game["round_history"][0]["inputs"][alice.id.to_string()],
+            "Rock"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_is_sent_to_game_followers() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+        ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+        let game_data = ctx.launch_room(alice.id, room_data.id).await.unwrap();
+
+        let (_, mut updates) = ctx.follow_game(alice.id, game_data.id).await.unwrap();
+        ctx.rename_player(bob.id, "Robert".to_string())
+            .await
+            .unwrap();
+
+        let update = updates.recv().await.unwrap();
+        assert_eq!(update.players[1].0.name, "Robert");
+        assert!(update.version > game_data.version);
+        let game_data = ctx.get_game_data(alice.id, game_data.id).await.unwrap();
+        assert_eq!(game_data.players[1].0.name, "Robert");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_games_locked_separately() {
+        let ctx = Arc::new(ServerContext::new());
+
+        let mut games = vec![];
+        for (host, guest) in [("Alice", "Bob"), ("Charlie", "Dave")] {
+            let host = ctx
+                .create_player_with_region(host.to_string(), None)
+                .await
+                .unwrap();
+            let guest = ctx
+                .create_player_with_region(guest.to_string(), None)
+                .await
+                .unwrap();
+            let room_data = ctx
+                .create_room(
+                    host.id,
+                    "test room".to_string(),
+                    None,
+                    RoomVisibility::Public,
+                    None,
+                )
+                .await
+                .unwrap();
+            ctx.join_room(guest.id, room_data.id, None).await.unwrap();
+            ctx.set_ready(host.id, room_data.id, true).await.unwrap();
+            ctx.set_ready(guest.id, room_data.id, true).await.unwrap();
+            let game_data = ctx.launch_room(host.id, room_data.id).await.unwrap();
+            games.push((host, game_data));
+        }
+
+        //A slow round holds the first game's lock
+        let busy_game = ctx
+            .with_data(|server_data| server_data.game_handle(games[0].1.id))
+            .await
+            .unwrap();
+        let (locked_sender, locked_receiver) = std::sync::mpsc::channel();
+        let (release_sender, release_receiver) = std::sync::mpsc::channel::<()>();
+        let busy_thread = std::thread::spawn(move || {
+            let _game_data = busy_game.lock().unwrap();
+            locked_sender.send(()).unwrap();
+            let _ = release_receiver.recv();
+        });
+        locked_receiver.recv().unwrap();
+
+        //Renaming its host waits for it, without holding up anyone else
+        let rename = tokio::spawn({
+            let ctx = ctx.clone();
+            let host_id = games[0].0.id;
+            async move { ctx.rename_player(host_id, "Alicia".to_string()).await }
+        });
+
+        //The lobby and the other game are not held up by it
+        let timeout = std::time::Duration::from_secs(1);
+        tokio::time::timeout(
+            timeout,
+            ctx.create_player_with_region("Eve".to_string(), None),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        tokio::time::timeout(timeout, ctx.get_rooms_filtered(None))
+            .await
+            .unwrap();
+        let (host, game_data) = &games[1];
+        tokio::time::timeout(
+            timeout,
+            ctx.play_round(host.id, game_data.id, ActionKind::Rock, None, None),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        //Nor are the sweeps over every game, which leave it for the next one
+        tokio::time::timeout(timeout, async {
+            ctx.expire_rounds().await;
+            ctx.forfeit_disconnected_players().await;
+            ctx.archive_ended_games().await;
+            ctx.player_analytics(host.id).await.unwrap();
+            ctx.metrics().await
+        })
+        .await
+        .unwrap();
+        let featured_game = tokio::time::timeout(timeout, ctx.featured_game())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(featured_game.id, game_data.id);
+        let games_list = tokio::time::timeout(timeout, ctx.get_games_list(true))
+            .await
+            .unwrap();
+        assert_eq!(
+            games_list.iter().map(|game| game.id).collect::<Vec<_>>(),
+            vec![game_data.id]
+        );
+
+        release_sender.send(()).unwrap();
+        busy_thread.join().unwrap();
+        rename.await.unwrap().unwrap();
+
+        let (host, game_data) = &games[0];
+        ctx.play_round(host.id, game_data.id, ActionKind::Rock, None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_leave_racing_launch() {
+        for _ in 0..50 {
+            let ctx = Arc::new(ServerContext::new());
+
+            let alice = ctx
+                .create_player_with_region("Alice".to_string(), None)
+                .await
+                .unwrap();
+            let bob = ctx
+                .create_player_with_region("Bob".to_string(), None)
+                .await
+                .unwrap();
+            let room_data = ctx
+                .create_room(
+                    alice.id,
+                    "test room".to_string(),
+                    None,
+                    RoomVisibility::Public,
+                    None,
+                )
+                .await
+                .unwrap();
+            ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+            ctx.set_ready(alice.id, room_data.id, true).await.unwrap();
+            ctx.set_ready(bob.id, room_data.id, true).await.unwrap();
+
+            let leave = tokio::spawn({
+                let ctx = ctx.clone();
+                async move { ctx.leave_room(bob.id, room_data.id).await }
+            });
+            let launch = tokio::spawn({
+                let ctx = ctx.clone();
+                async move { ctx.launch_room(alice.id, room_data.id).await }
+            });
+            let (leave, launch) = (leave.await.unwrap(), launch.await.unwrap());
+
+            //Exactly one of them wins, and a launched game always has its full roster
+            match launch {
+                Ok(game_data) => {
+                    assert!(leave.is_err());
+                    assert_eq!(game_data.players.len(), 2);
+                }
+                Err(_) => {
+                    assert!(leave.is_ok());
+                    let room_data = ctx.get_room_data(alice.id, room_data.id).await.unwrap();
+                    assert_eq!(room_data.players.len(), 1);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{IntoResponse, Response},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use types::{
+    net::{PresenceEvent, PresenceQuery},
+    PlayerData, PlayerId,
+};
+
+use crate::{error::ApiError, extract::ApiQuery, ServerContext};
+
+//Lobby presence: a player is online while they keep this WebSocket open.
+//Each connection is first told who is already online, then who comes online
+//or goes offline. Anything the client sends is ignored
+pub async fn presence_socket(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(presence_query): ApiQuery<PresenceQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if let Err(e) = ctx
+        .authenticate(presence_query.player_id, &presence_query.token)
+        .await
+    {
+        return ApiError::from(e).into_response();
+    }
+
+    ws.on_upgrade(move |socket| track_presence(socket, ctx, presence_query.player_id))
+}
+
+async fn track_presence(mut socket: WebSocket, ctx: Arc<ServerContext>, player_id: PlayerId) {
+    let already_online = ctx.online_players().await;
+    let Ok(presence_events) = ctx.connect_presence(player_id).await else {
+        return;
+    };
+
+    relay_presence(&mut socket, presence_events, already_online).await;
+
+    ctx.disconnect_presence(player_id).await;
+}
+
+//Returns once the connection is closed, whichever side closed it
+async fn relay_presence(
+    socket: &mut WebSocket,
+    mut presence_events: broadcast::Receiver<PresenceEvent>,
+    already_online: Vec<PlayerData>,
+) {
+    for player in already_online {
+        let event = PresenceEvent::Online { name: player.name };
+        if send_event(socket, &event).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => (),
+            },
+            event = presence_events.recv() => match event {
+                Ok(event) => {
+                    if send_event(socket, &event).await.is_err() {
+                        return;
+                    }
+                }
+                //A slow connection misses some events rather than holding up
+                //everyone else
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &PresenceEvent) -> Result<(), axum::Error> {
+    socket
+        .send(Message::Text(serde_json::to_string(event).unwrap()))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
+
+    use super::*;
+
+    type ClientSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    async fn next_event(socket: &mut ClientSocket) -> PresenceEvent {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let tungstenite::Message::Text(text) = socket.next().await.unwrap().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_presence_follows_connections() {
+        let ctx = Arc::new(ServerContext::new());
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .serve(crate::router(ctx.clone()).into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let url = |player_id: PlayerId, token: &str| {
+            format!("ws://{addr}/lobby/presence?player_id={player_id}&token={token}")
+        };
+
+        assert!(tokio_tungstenite::connect_async(url(alice.id, "wrong"))
+            .await
+            .is_err());
+        assert!(ctx.online_players().await.is_empty());
+
+        let alice_token = ctx.session_token(alice.id).await.unwrap();
+        let (mut alice_socket, _) = tokio_tungstenite::connect_async(url(alice.id, &alice_token))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&mut alice_socket).await,
+            PresenceEvent::Online {
+                name: "Alice".to_string()
+            }
+        );
+        assert_eq!(ctx.online_players().await, vec![alice.clone()]);
+
+        let bob_token = ctx.session_token(bob.id).await.unwrap();
+        let (mut bob_socket, _) = tokio_tungstenite::connect_async(url(bob.id, &bob_token))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&mut alice_socket).await,
+            PresenceEvent::Online {
+                name: "Bob".to_string()
+            }
+        );
+
+        bob_socket.close(None).await.unwrap();
+        assert_eq!(
+            next_event(&mut alice_socket).await,
+            PresenceEvent::Offline {
+                name: "Bob".to_string()
+            }
+        );
+        assert_eq!(ctx.online_players().await, vec![alice]);
+    }
+}
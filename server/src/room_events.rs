@@ -0,0 +1,169 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::State,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use futures_util::{stream, Stream, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use types::{
+    net::{JoinGetLeaveRoomQuery, RoomPublicData},
+    RoomData, RoomId,
+};
+
+use crate::{error::ApiError, extract::ApiQuery, ServerContext};
+
+//Server-Sent Events for the players waiting in a room, so they don't have
+//to poll /room/data. An `updated` event carries the room right away, then
+//again every time a player joins or leaves. A last `closed` event is sent
+//once the room is gone, because it emptied or its game was launched
+pub async fn room_events(
+    State(ctx): State<Arc<ServerContext>>,
+    ApiQuery(room_events_query): ApiQuery<JoinGetLeaveRoomQuery>,
+) -> Response {
+    let room_id = room_events_query.room_id;
+
+    match ctx.follow_room(room_events_query.player_id, room_id).await {
+        Ok((room_data, updates)) => Sse::new(room_event_stream(room_id, room_data, updates))
+            .keep_alive(KeepAlive::default())
+            .into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+fn room_event_stream(
+    room_id: RoomId,
+    room_data: RoomData,
+    updates: broadcast::Receiver<Option<RoomData>>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let updates = stream::unfold(Some(updates), move |updates| async move {
+        //Nothing is sent after the room is closed
+        let mut updates = updates?;
+        loop {
+            match updates.recv().await {
+                Ok(Some(room_data)) => return Some((updated_event(room_data), Some(updates))),
+                Ok(None) | Err(RecvError::Closed) => return Some((closed_event(room_id), None)),
+                //Each update is the whole room, the next one catches up
+                Err(RecvError::Lagged(_)) => (),
+            }
+        }
+    });
+
+    stream::once(async move { updated_event(room_data) })
+        .chain(updates)
+        .map(Ok)
+}
+
+fn updated_event(room_data: RoomData) -> Event {
+    Event::default()
+        .event("updated")
+        .json_data(RoomPublicData::from(room_data))
+        .unwrap()
+}
+
+//Browsers drop events without data, the id of the room is sent along
+fn closed_event(room_id: RoomId) -> Event {
+    Event::default().event("closed").data(room_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use axum::http::StatusCode;
+    use hyper::body::HttpBody;
+    use tower::ServiceExt;
+    use types::RoomVisibility;
+
+    use super::*;
+
+    //The name and the data of the next event, None once the stream is over
+    async fn next_event(body: &mut axum::body::BoxBody) -> Option<(String, String)> {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.data())
+            .await
+            .unwrap()?
+            .unwrap();
+        let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+
+        let field = |name: &str| {
+            chunk
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap()
+                .to_string()
+        };
+        Some((field("event:"), field("data:")))
+    }
+
+    fn player_names(data: &str) -> Vec<String> {
+        let room: serde_json::Value = serde_json::from_str(data).unwrap();
+        room["players"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|player| player["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_room_events_follow_joins_and_leaves() {
+        let ctx = Arc::new(ServerContext::new());
+        let alice = ctx
+            .create_player_with_region("Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = ctx
+            .create_player_with_region("Bob".to_string(), None)
+            .await
+            .unwrap();
+        let room_data = ctx
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                None,
+                RoomVisibility::Public,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let response = crate::router(ctx.clone())
+            .oneshot(
+                axum::http::Request::get(format!(
+                    "/room/events?player_id={}&room_id={}",
+                    alice.id, room_data.id
+                ))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body();
+
+        let (event, data) = next_event(&mut body).await.unwrap();
+        assert_eq!(event, "updated");
+        assert_eq!(player_names(&data), vec!["Alice"]);
+
+        ctx.join_room(bob.id, room_data.id, None).await.unwrap();
+        let (event, data) = next_event(&mut body).await.unwrap();
+        assert_eq!(event, "updated");
+        assert_eq!(player_names(&data), vec!["Alice", "Bob"]);
+
+        ctx.leave_room(bob.id, room_data.id).await.unwrap();
+        let (_, data) = next_event(&mut body).await.unwrap();
+        assert_eq!(player_names(&data), vec!["Alice"]);
+
+        //The room is deleted once its last player leaves
+        ctx.leave_room(alice.id, room_data.id).await.unwrap();
+        assert_eq!(
+            next_event(&mut body).await,
+            Some(("closed".to_string(), room_data.id.to_string()))
+        );
+        assert_eq!(next_event(&mut body).await, None);
+    }
+}
@@ -1,16 +1,65 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use itertools::Itertools;
+use itertools::{Either, Itertools};
+use types::chess::{ChessBoard, ChessColor};
 use types::*;
 
-use anyhow::anyhow;
-use anyhow::Result;
+use crate::clock::Clock;
+use crate::config::ServerConfig;
+
+use types::error::GameError;
+use types::net::PresenceEvent;
+
+//Each game has its own lock, so a round being played only holds up its own
+//game and never the lobby or the other games
+pub type GameHandle = Arc<Mutex<GameData>>;
+
+//What playing a move needs from the rest of the server. It is gathered under
+//the server lock, the move itself is then played holding only its game lock
+#[derive(Debug, Clone)]
+pub struct MoveContext {
+    pub now: Timestamp,
+    pub bots: Vec<(PlayerId, BotStrategy)>,
+}
 
 #[derive(Default, Debug)]
 pub struct ServerData {
-    pub games: Vec<GameData>,
-    pub players: Vec<PlayerData>,
-    pub rooms: Vec<RoomData>,
+    pub games: HashMap<GameId, GameHandle>,
+    //Games which ended more than the grace period ago. They can't be fetched
+    //anymore, but still count for the leaderboard, analytics and replays
+    pub archived_games: HashMap<GameId, GameData>,
+    pub players: HashMap<PlayerId, PlayerData>,
+    pub rooms: HashMap<RoomId, RoomData>,
+    //Guests playing a quick game, they are dropped once their game ends
+    //and never show up in the leaderboard
+    pub ephemeral_players: HashMap<PlayerId, PlayerData>,
+    //Bots are ephemeral players too, they go away with their game
+    pub bots: Vec<(PlayerId, BotStrategy)>,
+    pub matchmaking_queue: Vec<(PlayerId, Timestamp)>,
+    //Secret handed to each player when created, required on their mutating
+    //requests so player ids can't be used to impersonate them
+    pub session_tokens: HashMap<PlayerId, String>,
+    //Last time each player was heard from, through a heartbeat, any
+    //authenticated request or when created
+    pub last_seen: BTreeMap<PlayerId, Timestamp>,
+    //When the latest matchmaking games were made, to estimate the match rate
+    pub recent_matches: Vec<Timestamp>,
+    //Kept until the player fetches them
+    pub notifications: BTreeMap<PlayerId, Vec<Notification>>,
+    pub move_stats: BTreeMap<PlayerId, MoveStats>,
+    //Only registered players get stats, guests and bots don't outlive their game
+    pub player_stats: BTreeMap<PlayerId, PlayerStats>,
+    //Open presence connections of each player connected to the lobby
+    pub presence_connections: BTreeMap<PlayerId, usize>,
+    //Rooms are removed once launched, this is how their game is found back
+    pub launched_rooms: HashMap<RoomId, GameId>,
+    //The rematch of each ended game, so every player asking joins the same one
+    pub rematches: HashMap<GameId, GameId>,
+
+    pub clock: Clock,
+    pub config: ServerConfig,
 
     next_player_id: PlayerId,
     next_game_id: GameId,
@@ -24,23 +73,457 @@ impl ServerData {
         next_id
     }
 
-    pub fn create_player_with_name(&mut self, player_name: String) -> Result<PlayerData> {
-        if self.players.iter().any(|player| player.name == player_name) {
-            return Err(anyhow!("This name is already taken"));
+    pub fn create_player_with_region(
+        &mut self,
+        player_name: String,
+        region: Option<String>,
+    ) -> Result<PlayerData, GameError> {
+        let player_data = self.new_player_data(player_name, region)?;
+
+        self.players.insert(player_data.id, player_data.clone());
+        Ok(player_data)
+    }
+
+    pub fn create_ephemeral_player(
+        &mut self,
+        player_name: String,
+    ) -> Result<PlayerData, GameError> {
+        if !self.config.allow_ephemeral_players {
+            return Err(GameError::EphemeralPlayersDisabled);
+        }
+
+        let player_data = self.new_player_data(player_name, None)?;
+
+        self.ephemeral_players
+            .insert(player_data.id, player_data.clone());
+        Ok(player_data)
+    }
+
+    //Rooms and archived games hold their own copies of the player, they are
+    //renamed too. Live games are renamed one by one with rename_in_game, so a
+    //busy game doesn't hold up the server
+    pub fn rename_player(
+        &mut self,
+        player_id: PlayerId,
+        new_name: String,
+    ) -> Result<PlayerData, GameError> {
+        if self
+            .players
+            .values()
+            .chain(self.ephemeral_players.values())
+            .any(|player| player.name == new_name && player.id != player_id)
+        {
+            return Err(GameError::NameTaken);
+        }
+
+        let player_data = match self.players.get_mut(&player_id) {
+            Some(player_data) => player_data,
+            None => self
+                .ephemeral_players
+                .get_mut(&player_id)
+                .ok_or(GameError::UnknownPlayer)?,
+        };
+        player_data.name = new_name;
+        let player_data = player_data.clone();
+
+        for room_data in self.rooms.values_mut() {
+            Self::rename_copies(room_data.players.iter_mut(), &player_data);
+            Self::rename_copies(room_data.spectators.iter_mut(), &player_data);
+        }
+        for game_data in self.archived_games.values_mut() {
+            Self::rename_in_game(game_data, &player_data);
+        }
+
+        Ok(player_data)
+    }
+
+    //Games the player is in get a new version so pollers see the change
+    pub fn rename_in_game(game_data: &mut GameData, player_data: &PlayerData) -> bool {
+        let GameData {
+            players, observers, ..
+        } = game_data;
+        let in_players =
+            Self::rename_copies(players.iter_mut().map(|(player, _)| player), player_data);
+        let in_observers = Self::rename_copies(observers.iter_mut(), player_data);
+        if in_players || in_observers {
+            game_data.version += 1;
+        }
+
+        in_players || in_observers
+    }
+
+    fn rename_copies<'a>(
+        players: impl Iterator<Item = &'a mut PlayerData>,
+        player_data: &PlayerData,
+    ) -> bool {
+        let mut renamed = false;
+        for player in players.filter(|player| player.id == player_data.id) {
+            player.name = player_data.name.clone();
+            renamed = true;
+        }
+        renamed
+    }
+
+    fn new_player_data(
+        &mut self,
+        player_name: String,
+        region: Option<String>,
+    ) -> Result<PlayerData, GameError> {
+        if self
+            .players
+            .values()
+            .chain(self.ephemeral_players.values())
+            .any(|player| player.name == player_name)
+        {
+            return Err(GameError::NameTaken);
         }
 
         let player_data = PlayerData {
             id: self.create_player(),
             name: player_name,
+            region,
+            friends: vec![],
         };
+        self.last_seen.insert(player_data.id, self.clock.now());
+        self.session_tokens
+            .insert(player_data.id, Self::new_session_token());
 
-        self.players.push(player_data.clone());
         Ok(player_data)
     }
 
+    fn new_session_token() -> String {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).expect("No randomness source for session tokens");
+
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    pub fn session_token(&self, player_id: PlayerId) -> Option<String> {
+        self.session_tokens.get(&player_id).cloned()
+    }
+
+    //Unknown players get the same error as wrong tokens, so ids can't be
+    //probed this way
+    pub fn authenticate(&self, player_id: PlayerId, token: &str) -> Result<&PlayerData, GameError> {
+        match self.session_tokens.get(&player_id) {
+            Some(session_token) if session_token == token => {
+                self.find_player(player_id).ok_or(GameError::InvalidToken)
+            }
+            _ => Err(GameError::InvalidToken),
+        }
+    }
+
+    //No admin token configured means no admin at all
+    pub fn authenticate_admin(&self, token: &str) -> Result<(), GameError> {
+        match &self.config.admin_token {
+            Some(admin_token) if admin_token == token => Ok(()),
+            _ => Err(GameError::InvalidToken),
+        }
+    }
+
+    pub fn heartbeat(&mut self, player_id: PlayerId) -> Result<(), GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        self.last_seen.insert(player_id, self.clock.now());
+        Ok(())
+    }
+
+    //Players seen recently, or sitting in a room or a running game. Bots
+    //don't count. Players of a game busy with a move were just seen anyway
+    pub fn active_player_count(&self) -> usize {
+        let now = self.clock.now();
+
+        self.players
+            .values()
+            .chain(self.ephemeral_players.values())
+            .filter(|player| !self.bots.iter().any(|(bot_id, _)| *bot_id == player.id))
+            .filter(|player| {
+                let seen_recently = self.last_seen.get(&player.id).is_some_and(|last_seen| {
+                    now.saturating_sub(*last_seen) < self.config.idle_after_ms
+                });
+                let in_room = self
+                    .rooms
+                    .values()
+                    .any(|room| room.players.iter().any(|seated| seated.id == player.id));
+                let in_game = self.idle_games().any(|game| {
+                    game.status != GameStatus::Ended
+                        && game
+                            .players
+                            .iter()
+                            .any(|(seated, _)| seated.id == player.id)
+                });
+
+                seen_recently || in_room || in_game
+            })
+            .count()
+    }
+
+    fn lock_game(game: &GameHandle) -> MutexGuard<'_, GameData> {
+        game.lock().unwrap()
+    }
+
+    //Every game not busy with a move right now. The sweeps over all games
+    //use it, so a busy game holds up neither the server lock nor the worker
+    //running the sweep, it is picked up by the next sweep instead
+    fn idle_games(&self) -> impl Iterator<Item = MutexGuard<'_, GameData>> {
+        self.games.values().filter_map(|game| game.try_lock().ok())
+    }
+
+    //Live games not busy with a move, then archived ones
+    fn idle_and_archived_games(
+        &self,
+    ) -> impl Iterator<Item = Either<MutexGuard<'_, GameData>, &GameData>> {
+        self.idle_games()
+            .map(Either::Left)
+            .chain(self.archived_games.values().map(Either::Right))
+    }
+
+    fn insert_game(&mut self, game_data: GameData) {
+        self.games
+            .insert(game_data.id, Arc::new(Mutex::new(game_data)));
+    }
+
+    //Every live game, to be locked one at a time once the server lock is
+    //released
+    pub fn game_handles(&self) -> Vec<GameHandle> {
+        self.games.values().cloned().collect_vec()
+    }
+
+    pub fn game_handle(&self, game_id: GameId) -> Result<GameHandle, GameError> {
+        self.games
+            .get(&game_id)
+            .cloned()
+            .ok_or(GameError::UnknownGame)
+    }
+
+    fn find_player(&self, player_id: PlayerId) -> Option<&PlayerData> {
+        self.players
+            .get(&player_id)
+            .or_else(|| self.ephemeral_players.get(&player_id))
+    }
+
+    //A player can be connected from several tabs, only their first
+    //connection makes them come online
+    pub fn connect_presence(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<Option<PresenceEvent>, GameError> {
+        let name = self
+            .find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?
+            .name
+            .clone();
+
+        let connections = self.presence_connections.entry(player_id).or_default();
+        *connections += 1;
+
+        Ok((*connections == 1).then_some(PresenceEvent::Online { name }))
+    }
+
+    //The player goes offline once their last connection is closed
+    pub fn disconnect_presence(&mut self, player_id: PlayerId) -> Option<PresenceEvent> {
+        let connections = self.presence_connections.get_mut(&player_id)?;
+
+        *connections -= 1;
+        if *connections > 0 {
+            return None;
+        }
+
+        self.presence_connections.remove(&player_id);
+        let name = self.find_player(player_id)?.name.clone();
+        Some(PresenceEvent::Offline { name })
+    }
+
+    pub fn online_players(&self) -> Vec<PlayerData> {
+        self.presence_connections
+            .keys()
+            .filter_map(|player_id| self.find_player(*player_id).cloned())
+            .collect_vec()
+    }
+
+    //Rejected moves are counted per player, most of them come from buggy or
+    //cheating clients since the regular client only offers the legal actions
+    pub fn record_move(&mut self, player_id: PlayerId, result: &Result<GameData, GameError>) {
+        let stats = self.move_stats.entry(player_id).or_default();
+
+        match result {
+            Ok(_) => stats.accepted += 1,
+            Err(_) => stats.rejected += 1,
+        }
+    }
+
+    //Players with the most rejected moves first
+    pub fn suspicious_players(&self) -> Vec<(PlayerData, MoveStats)> {
+        self.move_stats
+            .iter()
+            .filter(|(_, stats)| {
+                stats.accepted + stats.rejected >= self.config.suspicious_min_moves
+                    && stats.rejection_rate() >= self.config.suspicious_rejection_rate
+            })
+            .filter_map(|(player_id, stats)| {
+                self.find_player(*player_id)
+                    .map(|player| (player.clone(), stats.clone()))
+            })
+            .sorted_by(|(_, a_stats), (_, b_stats)| b_stats.rejected.cmp(&a_stats.rejected))
+            .collect_vec()
+    }
+
+    //Games won by each player who won at least one. It only weighs on which
+    //game gets featured, a game busy with a move can be left out of it
+    fn wins_by_player(&self) -> HashMap<PlayerId, usize> {
+        self.idle_and_archived_games()
+            .filter(|game| game.status == GameStatus::Ended)
+            .filter_map(|game| game.winner)
+            .counts()
+    }
+
+    //Players who won the most games first, then the ones winning the most
+    //often. Only players who finished a game are ranked, guests and bots
+    //have no stats
+    pub fn leaderboard(&self, limit: usize) -> Vec<(PlayerData, PlayerStats)> {
+        self.player_stats
+            .iter()
+            .filter_map(|(player_id, stats)| {
+                self.players
+                    .get(player_id)
+                    .map(|player| (player.clone(), stats.clone()))
+            })
+            .sorted_by(|(a_player, a_stats), (b_player, b_stats)| {
+                b_stats
+                    .wins
+                    .cmp(&a_stats.wins)
+                    .then(b_stats.win_rate().total_cmp(&a_stats.win_rate()))
+                    .then(a_player.id.cmp(&b_player.id))
+            })
+            .take(limit)
+            .collect_vec()
+    }
+
+    //The running game most worth watching: the one between the players with
+    //the most wins put together, then the one with the closest scores. A
+    //game busy with a move sits this pick out
+    pub fn featured_game(&self) -> Result<GameData, GameError> {
+        let wins = self.wins_by_player();
+        let rating = |game: &GameData| {
+            game.players
+                .iter()
+                .map(|(player, _)| wins.get(&player.id).copied().unwrap_or(0))
+                .sum::<usize>()
+        };
+        let score_gap = |game: &GameData| {
+            game.players
+                .iter()
+                .map(|(_, score)| *score)
+                .minmax()
+                .into_option()
+                .map_or(0, |(lowest, highest)| highest - lowest)
+        };
+
+        self.idle_games()
+            .filter(|game| game.status == GameStatus::Running)
+            .max_by_key(|game| (rating(game), Reverse(score_gap(game)), Reverse(game.id)))
+            .map(|game| game.clone())
+            .ok_or(GameError::NoRunningGame)
+    }
+
+    pub fn player_analytics(&self, player_id: PlayerId) -> Result<PlayerAnalytics, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        //A game busy with a move isn't finished
+        let finished_games = self
+            .idle_and_archived_games()
+            .filter(|game| {
+                game.status == GameStatus::Ended
+                    && game
+                        .players
+                        .iter()
+                        .any(|(player, _)| player.id == player_id)
+            })
+            .sorted_by_key(|game| game.id)
+            .collect_vec();
+
+        let games_played = finished_games.len();
+        let games_won = finished_games
+            .iter()
+            .filter(|game| game.winner == Some(player_id))
+            .count();
+
+        let mut action_counts: Vec<(ActionKind, usize)> = vec![];
+        for action in finished_games
+            .iter()
+            .flat_map(|game| &game.round_history)
+            .filter_map(|round| round.inputs.get(&player_id))
+        {
+            match action_counts
+                .iter_mut()
+                .find(|(played, _)| played == action)
+            {
+                Some((_, count)) => *count += 1,
+                None => action_counts.push((action.clone(), 1)),
+            }
+        }
+        action_counts.sort_by(|(_, a_count), (_, b_count)| b_count.cmp(a_count));
+
+        Ok(PlayerAnalytics {
+            games_played,
+            games_won,
+            win_rate: if games_played == 0 {
+                0.0
+            } else {
+                games_won as f64 / games_played as f64
+            },
+            favorite_action: action_counts.first().map(|(action, _)| action.clone()),
+            action_counts,
+        })
+    }
+
+    pub fn add_friend(
+        &mut self,
+        player_id: PlayerId,
+        friend_id: PlayerId,
+    ) -> Result<PlayerData, GameError> {
+        if player_id == friend_id {
+            return Err(GameError::SelfFriend);
+        }
+
+        if !self.players.contains_key(&friend_id) {
+            return Err(GameError::UnknownPlayer);
+        }
+
+        let player_data = self
+            .players
+            .get_mut(&player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        if !player_data.friends.contains(&friend_id) {
+            player_data.friends.push(friend_id);
+        }
+
+        Ok(player_data.clone())
+    }
+
+    pub fn remove_friend(
+        &mut self,
+        player_id: PlayerId,
+        friend_id: PlayerId,
+    ) -> Result<PlayerData, GameError> {
+        let player_data = self
+            .players
+            .get_mut(&player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        player_data.friends.retain(|id| *id != friend_id);
+
+        Ok(player_data.clone())
+    }
+
     pub fn create_game(&mut self, room_data: RoomData) -> GameData {
         let game_id = self.next_game_id;
         self.next_game_id += 1;
+        let board = (room_data.settings.kind == GameKind::Chess).then(ChessBoard::starting);
+        let now = self.clock.now();
 
         GameData {
             settings: room_data.settings,
@@ -51,11 +534,30 @@ impl ServerData {
                 .collect_vec(),
             id: game_id,
             current_round: RoundData {
-                inputs: HashMap::new(),
+                inputs: BTreeMap::new(),
+                submitted_at: BTreeMap::new(),
                 result: None,
+                per_player: BTreeMap::new(),
+                score_deltas: BTreeMap::new(),
+                reveal_order: vec![],
+                timed_out: vec![],
+                started_at: now,
+                resolved_at: None,
             },
             round_history: vec![],
             status: GameStatus::Running,
+            winner: None,
+            winners: vec![],
+            observers: vec![],
+            eliminated: vec![],
+            forfeited: vec![],
+            version: 0,
+            board,
+            round_started_at: now,
+            paused_at: None,
+            ended_at: None,
+            tie_break_rounds: 0,
+            submission_keys: BTreeMap::new(),
         }
     }
 
@@ -64,349 +566,5305 @@ impl ServerData {
         player_id: PlayerId,
         room_name: String,
         settings: Option<GameSettings>,
-    ) -> anyhow::Result<RoomData> {
+    ) -> Result<RoomData, GameError> {
         let player_data = self
-            .players
-            .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
+            .find_player(player_id)
+            .cloned()
+            .ok_or(GameError::UnknownPlayer)?;
+
+        if let Some(settings) = &settings {
+            settings.validate()?;
+        }
+
+        //The host is the first player of a room, launching or emptying the
+        //room frees the slot
+        let hosted_rooms = self
+            .rooms
+            .values()
+            .filter(|room| room.host_id == player_id)
+            .count();
+        if hosted_rooms >= self.config.max_hosted_rooms {
+            return Err(GameError::TooManyRooms);
+        }
 
         let room_id = self.next_room_id;
         self.next_room_id += 1;
 
-        let room_data = RoomData {
+        let mut room_data = RoomData {
             id: room_id,
-            settings: settings.unwrap_or(GameSettings {
-                kind: GameKind::RockPaperScissors,
-                player_count: 2,
-                end_condition: EndCondition::FirstToScore(3),
-            }),
-            players: vec![player_data.clone()],
+            settings: settings.unwrap_or_default(),
+            host_id: player_id,
+            players: vec![],
+            spectators: vec![],
             name: room_name,
+            reserved_seats: vec![],
+            ready: vec![],
+            visibility: RoomVisibility::Public,
+            password: None,
         };
+        Self::seat_player(&mut room_data, player_data)?;
 
-        self.rooms.push(room_data.clone());
+        self.rooms.insert(room_data.id, room_data.clone());
         Ok(room_data)
     }
 
-    pub fn join_room(&mut self, player_id: PlayerId, room_id: RoomId) -> Result<RoomData> {
+    pub fn join_room_with_password(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        password: Option<&str>,
+    ) -> Result<(RoomData, bool), GameError> {
+        let now = self.clock.now();
+        let rejoin_grace_ms = self.config.rejoin_grace_ms;
+
         //Player must exist in players list
         let player_data = self
+            .find_player(player_id)
+            .cloned()
+            .ok_or(GameError::UnknownPlayer)?;
+
+        //Room must exist in rooms list
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if room_data
             .players
             .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
+            .any(|player| player.id == player_id)
+        {
+            return Err(GameError::AlreadyInRoom);
+        }
+
+        if let Some(room_password) = &room_data.password {
+            if password != Some(room_password.as_str()) {
+                return Err(GameError::WrongPassword);
+            }
+        }
+
+        //Seats of players who just left are kept for them during the grace period
+        room_data
+            .reserved_seats
+            .retain(|(_, left_at)| now.saturating_sub(*left_at) < rejoin_grace_ms);
+
+        let seats_reserved_for_others = room_data
+            .reserved_seats
+            .iter()
+            .filter(|(reserved_id, _)| *reserved_id != player_id)
+            .count();
+
+        if room_data.settings.player_count <= room_data.players.len() + seats_reserved_for_others {
+            return Err(GameError::RoomFull);
+        }
+
+        room_data
+            .reserved_seats
+            .retain(|(reserved_id, _)| *reserved_id != player_id);
+        Self::seat_player(room_data, player_data)?;
+        //Whoever was ready has to confirm again with the new player
+        room_data.ready.clear();
+        //A spectator taking a seat stops spectating
+        room_data
+            .spectators
+            .retain(|spectator| spectator.id != player_id);
+
+        let became_full = room_data.players.len() == room_data.settings.player_count;
+
+        Ok((room_data.clone(), became_full))
+    }
+
+    //Spectators don't take a seat, a full room still accepts them. They are
+    //never the host and are left out of the game once the room is launched
+    pub fn join_room_as_spectator(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        password: Option<&str>,
+    ) -> Result<RoomData, GameError> {
+        //Player must exist in players list
+        let player_data = self
+            .find_player(player_id)
+            .cloned()
+            .ok_or(GameError::UnknownPlayer)?;
 
         //Room must exist in rooms list
-        let room_data = self
-            .rooms
-            .iter_mut()
-            .find(|room| room.id == room_id)
-            .ok_or_else(|| anyhow!("Unknown room id"))?;
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
 
         if room_data
             .players
             .iter()
+            .chain(room_data.spectators.iter())
             .any(|player| player.id == player_id)
         {
-            return Err(anyhow!("Player already in the room"));
+            return Err(GameError::AlreadyInRoom);
         }
 
-        if room_data.settings.player_count as usize <= room_data.players.len() {
-            return Err(anyhow!("Room full"));
+        if let Some(room_password) = &room_data.password {
+            if password != Some(room_password.as_str()) {
+                return Err(GameError::WrongPassword);
+            }
         }
 
-        room_data.players.push(player_data.clone());
+        room_data.spectators.push(player_data);
 
         Ok(room_data.clone())
     }
 
-    pub fn leave_room(&mut self, player_id: PlayerId, room_id: RoomId) -> Result<()> {
-        //Player must exist in players list
-        self.players
+    //Every way of adding someone to a room goes through here, so that a player
+    //id can never take more than one seat
+    fn seat_player(room_data: &mut RoomData, player_data: PlayerData) -> Result<(), GameError> {
+        if room_data
+            .players
             .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
+            .any(|player| player.id == player_data.id)
+        {
+            return Err(GameError::AlreadyInRoom);
+        }
+
+        room_data.players.push(player_data);
+        Ok(())
+    }
+
+    pub fn leave_room(&mut self, player_id: PlayerId, room_id: RoomId) -> Result<(), GameError> {
+        let now = self.clock.now();
+
+        //Player must exist in players list
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
 
         //Room must exist in rooms list
-        let (room_index, room_data) = self
-            .rooms
-            .iter_mut()
-            .enumerate()
-            .find(|(_, room)| room.id == room_id)
-            .ok_or_else(|| anyhow!("Unknown room id"))?;
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        //Spectators have no seat to keep
+        if room_data
+            .spectators
+            .iter()
+            .any(|spectator| spectator.id == player_id)
+        {
+            room_data
+                .spectators
+                .retain(|spectator| spectator.id != player_id);
+            return Ok(());
+        }
 
         if !room_data
             .players
             .iter()
             .any(|player| player.id == player_id)
         {
-            return Err(anyhow!("Player already left the room"));
+            return Err(GameError::AlreadyLeftRoom);
         }
 
+        let was_host = room_data.host_id == player_id;
         room_data
             .players
             .retain_mut(|player| player.id != player_id);
+        room_data.ready.retain(|ready_id| *ready_id != player_id);
+
+        //Without a human left, the room is deleted along with its bots
+        let Some(human_index) = room_data
+            .players
+            .iter()
+            .position(|player| !self.bots.iter().any(|(bot_id, _)| *bot_id == player.id))
+        else {
+            let bot_ids = room_data.players.iter().map(|bot| bot.id).collect_vec();
+            self.rooms.remove(&room_id);
+            self.drop_bots(&bot_ids);
+            return Ok(());
+        };
+
+        room_data.reserved_seats.push((player_id, now));
+
+        //The host is the first player, the next human in line takes over
+        if was_host {
+            let new_host = room_data.players.remove(human_index);
+            let new_host_id = new_host.id;
+            room_data.players.insert(0, new_host);
+            room_data.host_id = new_host_id;
+            self.notify(new_host_id, Notification::PromotedToHost { room_id });
+        }
+
+        Ok(())
+    }
+
+    //Unlike a player leaving, a kicked player doesn't keep a reserved seat
+    pub fn kick_player(
+        &mut self,
+        host_id: PlayerId,
+        room_id: RoomId,
+        target_id: PlayerId,
+    ) -> Result<RoomData, GameError> {
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if room_data.host_id != host_id {
+            return Err(GameError::NotHost);
+        }
+
+        //The host leaves the room rather than kicking themselves
+        if !room_data.players[1..]
+            .iter()
+            .any(|player| player.id == target_id)
+        {
+            return Err(GameError::NotInRoom);
+        }
+
+        room_data.players.retain(|player| player.id != target_id);
+        room_data
+            .reserved_seats
+            .retain(|(player_id, _)| *player_id != target_id);
+        room_data.ready.retain(|ready_id| *ready_id != target_id);
+        let room_data = room_data.clone();
+
+        self.drop_bots(&[target_id]);
+
+        Ok(room_data)
+    }
+
+    fn notify(&mut self, player_id: PlayerId, notification: Notification) {
+        self.notifications
+            .entry(player_id)
+            .or_default()
+            .push(notification);
+    }
+
+    //Hands over the player's pending notifications, oldest first
+    pub fn take_notifications(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<Vec<Notification>, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        Ok(self.notifications.remove(&player_id).unwrap_or_default())
+    }
+
+    //Moves every player of the source room into the target room, which keeps
+    //its own settings, then deletes the source room
+    pub fn merge_rooms(
+        &mut self,
+        source_room_id: RoomId,
+        target_room_id: RoomId,
+    ) -> Result<RoomData, GameError> {
+        if source_room_id == target_room_id {
+            return Err(GameError::SelfMerge);
+        }
+
+        let source_room = self
+            .rooms
+            .get(&source_room_id)
+            .ok_or(GameError::UnknownRoom)?;
+        let target_room = self
+            .rooms
+            .get(&target_room_id)
+            .ok_or(GameError::UnknownRoom)?;
+
+        if source_room.settings.kind != target_room.settings.kind {
+            return Err(GameError::GameKindMismatch);
+        }
+
+        if source_room.players.len() + target_room.players.len() > target_room.settings.player_count
+        {
+            return Err(GameError::NotEnoughSeats);
+        }
 
-        if room_data.players.is_empty() {
-            self.rooms.remove(room_index);
+        let mut merged_room = target_room.clone();
+        for player_data in source_room.players.clone() {
+            Self::seat_player(&mut merged_room, player_data)?;
         }
+        merged_room.ready.clear();
+        for spectator_data in source_room.spectators.clone() {
+            if !merged_room
+                .players
+                .iter()
+                .chain(merged_room.spectators.iter())
+                .any(|player| player.id == spectator_data.id)
+            {
+                merged_room.spectators.push(spectator_data);
+            }
+        }
+
+        self.rooms.insert(target_room_id, merged_room.clone());
+        self.rooms.remove(&source_room_id);
+
+        Ok(merged_room)
+    }
+
+    pub fn get_room_data(
+        &self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<RoomData, GameError> {
+        //Player must exist in players list
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        //Room must exist in rooms list
+        let room_data = self.rooms.get(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if !room_data
+            .players
+            .iter()
+            .chain(room_data.spectators.iter())
+            .any(|player| player.id == player_id)
+        {
+            return Err(GameError::NotInRoom);
+        }
+
+        Ok(room_data.clone())
+    }
+
+    //Queues the player for a quick two-player game. Players from the same
+    //region are matched first, anyone can be matched once one of the two
+    //has been waiting for long enough. Returns the game once matched
+    pub fn matchmake(&mut self, player_id: PlayerId) -> Result<Option<GameData>, GameError> {
+        let now = self.clock.now();
+
+        let player_data = self
+            .find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?
+            .clone();
+
+        if !self
+            .matchmaking_queue
+            .iter()
+            .any(|(queued_id, _)| *queued_id == player_id)
+        {
+            self.matchmaking_queue.push((player_id, now));
+        }
+
+        let queued_since = |queued_id: PlayerId| {
+            self.matchmaking_queue
+                .iter()
+                .find(|(id, _)| *id == queued_id)
+                .map(|(_, since)| *since)
+                .unwrap_or(now)
+        };
+        let has_waited_enough =
+            |since: Timestamp| now.saturating_sub(since) >= self.config.cross_region_wait_ms;
+        let player_waited_enough = has_waited_enough(queued_since(player_id));
+
+        let candidates = self
+            .matchmaking_queue
+            .iter()
+            .filter(|(queued_id, _)| *queued_id != player_id)
+            .filter_map(|(queued_id, since)| {
+                self.find_player(*queued_id)
+                    .map(|opponent| (opponent, *since))
+            })
+            .collect_vec();
+
+        let opponent = candidates
+            .iter()
+            .find(|(opponent, _)| opponent.region == player_data.region)
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .find(|(_, since)| player_waited_enough || has_waited_enough(*since))
+            })
+            .map(|(opponent, _)| (*opponent).clone());
+
+        let Some(opponent) = opponent else {
+            return Ok(None);
+        };
+
+        self.matchmaking_queue
+            .retain(|(queued_id, _)| *queued_id != player_id && *queued_id != opponent.id);
+
+        let room_id = self.next_room_id;
+        self.next_room_id += 1;
+
+        let game_data = self.create_game(RoomData {
+            id: room_id,
+            name: "Matchmaking".to_string(),
+            settings: GameSettings::default(),
+            host_id: opponent.id,
+            players: vec![opponent, player_data],
+            spectators: vec![],
+            reserved_seats: vec![],
+            ready: vec![],
+            visibility: RoomVisibility::Public,
+            password: None,
+        });
+        self.insert_game(game_data.clone());
+
+        self.recent_matches.push(now);
+        self.recent_matches.retain(|matched_at| {
+            now.saturating_sub(*matched_at) < self.config.match_rate_window_ms
+        });
+
+        Ok(Some(game_data))
+    }
+
+    //Rough estimate of how long the player will stay in the matchmaking
+    //queue : a queued player from the same region is an instant match,
+    //players from other regions become available once the cross region wait
+    //is over, and newcomers are expected at the recent match rate
+    pub fn estimate_wait(&self, player_id: PlayerId) -> Result<Timestamp, GameError> {
+        let now = self.clock.now();
+
+        let player_data = self
+            .find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        let waited = |queued_id: PlayerId| {
+            self.matchmaking_queue
+                .iter()
+                .find(|(id, _)| *id == queued_id)
+                .map(|(_, since)| now.saturating_sub(*since))
+                .unwrap_or(0)
+        };
+        let player_waited = waited(player_id);
+
+        let candidates = self
+            .matchmaking_queue
+            .iter()
+            .filter(|(queued_id, _)| *queued_id != player_id)
+            .filter_map(|(queued_id, _)| self.find_player(*queued_id))
+            .collect_vec();
+
+        if candidates
+            .iter()
+            .any(|opponent| opponent.region == player_data.region)
+        {
+            return Ok(0);
+        }
+
+        let cross_region_wait = candidates
+            .iter()
+            .map(|opponent| {
+                self.config
+                    .cross_region_wait_ms
+                    .saturating_sub(player_waited.max(waited(opponent.id)))
+            })
+            .min();
+
+        let recent_matches = self
+            .recent_matches
+            .iter()
+            .filter(|matched_at| {
+                now.saturating_sub(**matched_at) < self.config.match_rate_window_ms
+            })
+            .count() as Timestamp;
+        let next_arrival = self.config.match_rate_window_ms / (recent_matches + 1);
+
+        Ok(cross_region_wait.map_or(next_arrival, |wait| wait.min(next_arrival)))
+    }
+
+    pub fn launch_readiness(&self, player_id: PlayerId, room_id: RoomId) -> LaunchReadiness {
+        if self.find_player(player_id).is_none() {
+            return LaunchReadiness::UnknownPlayer;
+        }
+
+        let Some(room_data) = self.rooms.get(&room_id) else {
+            return LaunchReadiness::UnknownRoom;
+        };
+
+        if !room_data
+            .players
+            .iter()
+            .any(|player| player.id == player_id)
+        {
+            return LaunchReadiness::NotInRoom;
+        }
+        if room_data.host_id != player_id {
+            return LaunchReadiness::NotHost;
+        }
+
+        if room_data.players.len() != room_data.settings.player_count {
+            let mut recovery = vec![];
+            if !room_data.settings.kind.available_actions().is_empty() {
+                recovery.push(LaunchRecovery::FillWithBots {
+                    bots: room_data.settings.player_count - room_data.players.len(),
+                });
+            }
+            if room_data.players.len() >= 2 {
+                recovery.push(LaunchRecovery::ReduceCount {
+                    player_count: room_data.players.len(),
+                });
+            }
+
+            return LaunchReadiness::NotFull {
+                players: room_data.players.len(),
+                required: room_data.settings.player_count,
+                recovery,
+            };
+        }
+
+        let waiting = self.players_not_ready(room_data);
+        if waiting > 0 {
+            return LaunchReadiness::NotReady { waiting };
+        }
+
+        LaunchReadiness::Ready
+    }
+
+    //The host confirms by launching, bots are always ready
+    fn players_not_ready(&self, room_data: &RoomData) -> usize {
+        room_data
+            .players
+            .iter()
+            .filter(|player| {
+                player.id != room_data.host_id
+                    && !room_data.ready.contains(&player.id)
+                    && !self.is_bot(player.id)
+            })
+            .count()
+    }
+
+    pub fn set_ready(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        ready: bool,
+    ) -> Result<RoomData, GameError> {
+        //Player must exist in players list
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        //Room must exist in rooms list
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if !room_data
+            .players
+            .iter()
+            .any(|player| player.id == player_id)
+        {
+            return Err(GameError::NotInRoom);
+        }
+
+        room_data.ready.retain(|ready_id| *ready_id != player_id);
+        if ready {
+            room_data.ready.push(player_id);
+        }
+
+        Ok(room_data.clone())
+    }
+
+    //Lets the host launch with the players already there instead of waiting
+    //for seats that will never be taken
+    pub fn reduce_player_count(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<RoomData, GameError> {
+        match self.launch_readiness(player_id, room_id) {
+            LaunchReadiness::Ready
+            | LaunchReadiness::NotFull { .. }
+            | LaunchReadiness::NotReady { .. } => (),
+            readiness => readiness.into_result()?,
+        }
+
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if room_data.players.len() < 2 {
+            return Err(GameError::NotEnoughPlayers);
+        }
+
+        room_data.settings.player_count = room_data.players.len();
+
+        Ok(room_data.clone())
+    }
+
+    pub fn launch_room(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+    ) -> Result<GameData, GameError> {
+        self.launch_readiness(player_id, room_id).into_result()?;
+
+        let room_data = self.rooms.remove(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        //Re-check the roster at the commit point, a leave must not slip in
+        //between the readiness check and the game creation
+        if room_data.players.len() != room_data.settings.player_count {
+            self.rooms.insert(room_id, room_data);
+            return Err(GameError::RoomNotFull);
+        }
+
+        let mut game_data = self.create_game(room_data);
+        Self::play_bot_moves(&mut game_data, &self.bots);
+        self.insert_game(game_data.clone());
+        self.launched_rooms.insert(room_id, game_data.id);
+
+        Ok(game_data)
+    }
+
+    pub fn games_for_room_history(&self, room_id: RoomId) -> Result<GameId, GameError> {
+        self.launched_rooms
+            .get(&room_id)
+            .copied()
+            .ok_or(GameError::UnknownRoom)
+    }
+
+    //Fills the empty seats of the room with bots before launching it
+    pub fn launch_room_with_bots(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        strategy: BotStrategy,
+    ) -> Result<GameData, GameError> {
+        match self.launch_readiness(player_id, room_id) {
+            LaunchReadiness::Ready | LaunchReadiness::NotFull { .. } => (),
+            readiness => readiness.into_result()?,
+        }
+
+        let room_data = self.rooms.get(&room_id).ok_or(GameError::UnknownRoom)?;
+        if room_data
+            .players
+            .iter()
+            .all(|player| self.bots.iter().any(|(bot_id, _)| *bot_id == player.id))
+        {
+            return Err(GameError::NoHumanPlayer);
+        }
+        //Bots can only pick among a fixed set of actions
+        if !strategy.is_available_in(&room_data.settings.kind) {
+            return Err(GameError::ActionNotAvailable);
+        }
+        //Checked before seating the bots, which would be left in the room
+        if self.players_not_ready(room_data) > 0 {
+            return Err(GameError::PlayersNotReady);
+        }
+        let free_seats = room_data.settings.player_count - room_data.players.len();
+        self.seat_bots(room_id, free_seats, strategy)?;
+
+        self.launch_room(player_id, room_id)
+    }
+
+    //Bots can only pick among a fixed set of actions, and only take free seats
+    pub fn seat_bots(
+        &mut self,
+        room_id: RoomId,
+        bot_count: usize,
+        strategy: BotStrategy,
+    ) -> Result<RoomData, GameError> {
+        let room_data = self.rooms.get(&room_id).ok_or(GameError::UnknownRoom)?;
+        if bot_count == 0 {
+            return Ok(room_data.clone());
+        }
+        if !strategy.is_available_in(&room_data.settings.kind) {
+            return Err(GameError::ActionNotAvailable);
+        }
+        if room_data.players.len() + bot_count > room_data.settings.player_count {
+            return Err(GameError::NotEnoughSeats);
+        }
+
+        for _ in 0..bot_count {
+            let bot_name = format!("Bot {}", self.next_player_id);
+            let bot_data = self.new_player_data(bot_name, None)?;
+            self.ephemeral_players.insert(bot_data.id, bot_data.clone());
+            self.bots.push((bot_data.id, strategy.clone()));
+
+            let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+            Self::seat_player(room_data, bot_data)?;
+        }
+
+        self.rooms
+            .get(&room_id)
+            .cloned()
+            .ok_or(GameError::UnknownRoom)
+    }
+
+    fn is_bot(&self, player_id: PlayerId) -> bool {
+        self.bots.iter().any(|(bot_id, _)| *bot_id == player_id)
+    }
+
+    //Bots seated in a room which is gone without a game go away with it
+    fn drop_bots(&mut self, player_ids: &[PlayerId]) {
+        let bot_ids = player_ids
+            .iter()
+            .copied()
+            .filter(|player_id| self.is_bot(*player_id))
+            .collect_vec();
+
+        self.bots.retain(|(bot_id, _)| !bot_ids.contains(bot_id));
+        self.ephemeral_players
+            .retain(|player_id, _| !bot_ids.contains(player_id));
+    }
+
+    //Starts a game against a recording of a finished game: the other player
+    //of that game (its first player when practicing on someone else's game)
+    //is played back by a bot, round after round
+    pub fn start_replay_practice(
+        &mut self,
+        player_id: PlayerId,
+        source_game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let player_data = self
+            .find_player(player_id)
+            .cloned()
+            .ok_or(GameError::UnknownPlayer)?;
+
+        let source_game = match self.games.get(&source_game_id) {
+            Some(game) => Self::lock_game(game).clone(),
+            None => self
+                .archived_games
+                .get(&source_game_id)
+                .cloned()
+                .ok_or(GameError::UnknownGame)?,
+        };
+
+        if source_game.status != GameStatus::Ended {
+            return Err(GameError::GameStillRunning);
+        }
+
+        let recorded_player = source_game
+            .players
+            .iter()
+            .map(|(player, _)| player)
+            .find(|player| player.id != player_id)
+            .ok_or(GameError::NotInGame)?;
+
+        let recorded_actions = source_game
+            .round_history
+            .iter()
+            .filter_map(|round| round.inputs.get(&recorded_player.id).cloned())
+            .collect_vec();
+        if recorded_actions.is_empty() {
+            return Err(GameError::NoMoves);
+        }
+
+        let settings = GameSettings {
+            player_count: 2,
+            end_condition: EndCondition::TotalRounds(recorded_actions.len()),
+            scoring_mode: ScoringMode::Pairwise,
+            tie_break: TieBreak::Draw,
+            ..source_game.settings
+        };
+        let ghost_name = format!("{} (replay {})", recorded_player.name, self.next_player_id);
+
+        let ghost_data = self.new_player_data(ghost_name, None)?;
+        self.ephemeral_players
+            .insert(ghost_data.id, ghost_data.clone());
+        self.bots
+            .push((ghost_data.id, BotStrategy::Replay(recorded_actions)));
+
+        let room_id = self.next_room_id;
+        self.next_room_id += 1;
+
+        let mut game_data = self.create_game(RoomData {
+            id: room_id,
+            name: "Replay practice".to_string(),
+            settings,
+            host_id: player_data.id,
+            players: vec![player_data, ghost_data],
+            spectators: vec![],
+            reserved_seats: vec![],
+            ready: vec![],
+            visibility: RoomVisibility::Public,
+            password: None,
+        });
+        Self::play_bot_moves(&mut game_data, &self.bots);
+        self.insert_game(game_data.clone());
+
+        Ok(game_data)
+    }
+
+    //A new game between the players of an ended game, with the same
+    //settings. Players asking for the rematch of a game all get the same one
+    pub fn rematch(&mut self, player_id: PlayerId, game_id: GameId) -> Result<GameData, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        let game_data = match self.games.get(&game_id) {
+            Some(game) => Self::lock_game(game).clone(),
+            None => self
+                .archived_games
+                .get(&game_id)
+                .cloned()
+                .ok_or(GameError::UnknownGame)?,
+        };
+
+        if !game_data
+            .players
+            .iter()
+            .any(|(player, _)| player.id == player_id)
+        {
+            return Err(GameError::NotInGame);
+        }
+
+        if game_data.status != GameStatus::Ended {
+            return Err(GameError::GameStillRunning);
+        }
+
+        if let Some(rematch_id) = self.rematches.get(&game_id) {
+            return match self.games.get(rematch_id) {
+                Some(rematch) => Ok(Self::lock_game(rematch).clone()),
+                None => self
+                    .archived_games
+                    .get(rematch_id)
+                    .cloned()
+                    .ok_or(GameError::UnknownGame),
+            };
+        }
+
+        //Guests and bots went away with the game, they can't be seated again.
+        //The others are seated as they are now, in case they were renamed
+        let players = game_data
+            .players
+            .iter()
+            .map(|(player, _)| self.players.get(&player.id).cloned())
+            .collect::<Option<Vec<_>>>()
+            .ok_or(GameError::PlayerGone)?;
+
+        let room_id = self.next_room_id;
+        self.next_room_id += 1;
+
+        let rematch = self.create_game(RoomData {
+            id: room_id,
+            name: "Rematch".to_string(),
+            settings: game_data.settings,
+            host_id: players[0].id,
+            players,
+            spectators: vec![],
+            reserved_seats: vec![],
+            ready: vec![],
+            visibility: RoomVisibility::Public,
+            password: None,
+        });
+        self.insert_game(rematch.clone());
+        self.rematches.insert(game_id, rematch.id);
+
+        Ok(rematch)
+    }
+
+    //Bots play as soon as a round starts, so the round resolves when the
+    //last human plays
+    fn play_bot_moves(game_data: &mut GameData, bots: &[(PlayerId, BotStrategy)]) {
+        for (bot_id, strategy) in bots {
+            if game_data
+                .remaining_players()
+                .any(|player| player.id == *bot_id)
+            {
+                let action = strategy.pick_action(game_data, *bot_id, Self::random_number());
+                game_data.current_round.inputs.insert(*bot_id, action);
+            }
+        }
+    }
+
+    fn random_number() -> usize {
+        let mut bytes = [0u8; 8];
+        getrandom::getrandom(&mut bytes).expect("No randomness source for bots");
+
+        u64::from_le_bytes(bytes) as usize
+    }
+
+    pub fn get_game_data(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        let game_data = Self::lock_game(&self.game_handle(game_id)?).clone();
+
+        if !game_data
+            .players
+            .iter()
+            .any(|(player, _)| player.id == player_id)
+        {
+            return Err(GameError::NotInGame);
+        }
+
+        Ok(game_data)
+    }
+
+    //The requested games the player takes part in, in the requested order.
+    //Unknown games and other players' games are left out
+    pub fn get_games_batch(
+        &self,
+        player_id: PlayerId,
+        game_ids: &[GameId],
+    ) -> Result<Vec<GameData>, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        Ok(game_ids
+            .iter()
+            .unique()
+            .filter_map(|game_id| self.get_game_data(player_id, *game_id).ok())
+            .collect_vec())
+    }
+
+    //Only resolved rounds can be fetched, the inputs of the round being
+    //played must stay secret
+    pub fn get_round(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+        round_index: usize,
+    ) -> Result<RoundData, GameError> {
+        self.get_game_data(player_id, game_id)?
+            .round_history
+            .get(round_index)
+            .cloned()
+            .ok_or(GameError::UnknownRound)
+    }
+
+    //Anyone can watch a game, the view is the same one observers get
+    pub fn get_public_game_data(&self, game_id: GameId) -> Result<GameData, GameError> {
+        Ok(Self::lock_game(&self.game_handle(game_id)?).clone())
+    }
+
+    //Players of the game can't spectate it, they use get_game_data instead
+    pub fn spectate_game(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let player_data = self
+            .find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?
+            .clone();
+
+        let game = self.game_handle(game_id)?;
+        let mut game_data = Self::lock_game(&game);
+
+        if game_data
+            .players
+            .iter()
+            .any(|(player, _)| player.id == player_id)
+        {
+            return Err(GameError::AlreadyInGame);
+        }
+
+        if !game_data
+            .observers
+            .iter()
+            .any(|observer| observer.id == player_id)
+        {
+            game_data.observers.push(player_data);
+            game_data.version += 1;
+        }
+
+        Ok(game_data.clone())
+    }
+
+    //Voids a disputed round, the players play it again from scratch. Only
+    //the round in progress can be reset, resolved ones are final
+    pub fn reset_current_round(
+        &mut self,
+        host_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.find_player(host_id).ok_or(GameError::UnknownPlayer)?;
+
+        let game = self.game_handle(game_id)?;
+        let mut game_data = Self::lock_game(&game);
+        let game_data = &mut *game_data;
+
+        let Some((host, _)) = game_data.players.first() else {
+            return Err(GameError::NotInGame);
+        };
+        if host.id != host_id {
+            return Err(
+                if game_data
+                    .players
+                    .iter()
+                    .any(|(player, _)| player.id == host_id)
+                {
+                    GameError::NotHost
+                } else {
+                    GameError::NotInGame
+                },
+            );
+        }
+
+        if game_data.status != GameStatus::Running {
+            return Err(GameError::GameNotRunning);
+        }
+
+        game_data.round_started_at = self.clock.now();
+        game_data.current_round = RoundData {
+            started_at: game_data.round_started_at,
+            ..RoundData::default()
+        };
+        //Chess moves are applied as soon as they are played, the board is
+        //rebuilt from the resolved rounds
+        if let Some(board) = &mut game_data.board {
+            *board = ChessBoard::starting();
+            for round in &game_data.round_history {
+                for player_id in &round.reveal_order {
+                    if let Some(ActionKind::Move { from, to }) = round.inputs.get(player_id) {
+                        board.apply_move(*from, *to);
+                    }
+                }
+            }
+        }
+        Self::play_bot_moves(game_data, &self.bots);
+        game_data.version += 1;
+
+        Ok(game_data.clone())
+    }
+
+    #[cfg(test)]
+    pub fn play_round(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+        action: ActionKind,
+    ) -> Result<GameData, GameError> {
+        self.play_round_at_version(player_id, game_id, action, None)
+    }
+
+    //Same as play_round, but only if the game is still at the version the
+    //player based their move on
+    #[cfg(test)]
+    pub fn play_round_at_version(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+        action: ActionKind,
+        expected_version: Option<u64>,
+    ) -> Result<GameData, GameError> {
+        let result = self
+            .prepare_move(player_id, game_id)
+            .and_then(|(game, context)| {
+                Self::apply_move(&game, player_id, action, expected_version, None, &context)
+            });
+
+        self.settle_move(player_id, result)
+    }
+
+    fn move_context(&self) -> MoveContext {
+        MoveContext {
+            now: self.clock.now(),
+            bots: self.bots.clone(),
+        }
+    }
+
+    //Everything a move needs from the server, the move can then be played
+    //with play_move while holding only the lock of its game
+    pub fn prepare_move(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<(GameHandle, MoveContext), GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        Ok((self.game_handle(game_id)?, self.move_context()))
+    }
+
+    //Plays the move holding only the lock of its game, the move must then
+    //be settled with settle_move. Also tells whether this move ended the
+    //game, a resubmitted move finds it already ended
+    pub fn apply_move(
+        game: &GameHandle,
+        player_id: PlayerId,
+        action: ActionKind,
+        expected_version: Option<u64>,
+        idempotency_key: Option<&str>,
+        context: &MoveContext,
+    ) -> Result<(GameData, bool), GameError> {
+        let mut game_data = Self::lock_game(game);
+        let was_running = game_data.status != GameStatus::Ended;
+        Self::play_move(
+            &mut game_data,
+            player_id,
+            action,
+            expected_version,
+            idempotency_key,
+            context,
+        )?;
+        let ended_game = was_running && game_data.status == GameStatus::Ended;
+
+        Ok((game_data.clone(), ended_game))
+    }
+
+    //Counts the move, and releases the players if it ended the game
+    pub fn settle_move(
+        &mut self,
+        player_id: PlayerId,
+        result: Result<(GameData, bool), GameError>,
+    ) -> Result<GameData, GameError> {
+        let result = result.map(|(game_data, ended_game)| {
+            if ended_game {
+                self.release_players(&game_data);
+            }
+            game_data
+        });
+        self.record_move(player_id, &result);
+
+        result
+    }
+
+    //Once the game has ended, its players must be released with
+    //release_players
+    pub fn play_move(
+        game_data: &mut GameData,
+        player_id: PlayerId,
+        action: ActionKind,
+        expected_version: Option<u64>,
+        idempotency_key: Option<&str>,
+        context: &MoveContext,
+    ) -> Result<(), GameError> {
+        //The action sent again is already played, even if its round was
+        //resolved since then or the game moved past the expected version
+        if idempotency_key.is_some()
+            && game_data
+                .submission_keys
+                .get(&player_id)
+                .map(String::as_str)
+                == idempotency_key
+        {
+            return Ok(());
+        }
+
+        if expected_version.is_some_and(|expected_version| expected_version != game_data.version) {
+            return Err(GameError::StaleVersion);
+        }
+
+        //Re-sending the action already played this round is a no-op, it
+        //must not count as a change nor trip the lock-in and cooldown checks.
+        //A paused or ended game still refuses it
+        Self::ensure_running(game_data)?;
+        if game_data.current_round.inputs.get(&player_id) == Some(&action) {
+            return Ok(());
+        }
+
+        Self::validate_action(game_data, player_id, &action, context.now)?;
+
+        //All the changes are made on a copy, which replaces the stored game
+        //only once the whole move has been applied
+        let mut new_game_data = game_data.clone();
+
+        Self::prune_stale_inputs(&mut new_game_data);
+        if let (Some(board), ActionKind::Move { from, to }) = (&mut new_game_data.board, &action) {
+            board.apply_move(*from, *to);
+        }
+        new_game_data.current_round.inputs.insert(player_id, action);
+        new_game_data
+            .current_round
+            .submitted_at
+            .insert(player_id, context.now);
+        if let Some(idempotency_key) = idempotency_key {
+            new_game_data
+                .submission_keys
+                .insert(player_id, idempotency_key.to_string());
+        }
+
+        if new_game_data.required_submissions_remaining() == 0 {
+            Self::finish_round(&mut new_game_data, context);
+        }
+
+        new_game_data.version += 1;
+        *game_data = new_game_data;
+
+        Ok(())
+    }
+
+    fn finish_round(game_data: &mut GameData, context: &MoveContext) {
+        game_data.current_round.resolved_at = Some(context.now);
+        Self::resolve_current_round(game_data);
+        Self::update_game_status(game_data);
+
+        if game_data.status == GameStatus::Running {
+            game_data.round_started_at = context.now;
+            game_data.current_round.started_at = context.now;
+            Self::play_bot_moves(game_data, &context.bots);
+        }
+
+        if game_data.status == GameStatus::Ended {
+            game_data.ended_at = Some(context.now);
+        }
+    }
+
+    //Moves the games which ended more than the grace period ago out of the
+    //live games, so players get some time to look at the final result
+    pub fn archive_ended_games(&mut self) -> Vec<GameId> {
+        let now = self.clock.now();
+
+        let archived = self
+            .idle_games()
+            .filter(|game| {
+                game.ended_at.is_some_and(|ended_at| {
+                    now.saturating_sub(ended_at) >= self.config.ended_game_grace_ms
+                })
+            })
+            .map(|game| game.id)
+            .sorted()
+            .collect_vec();
+
+        for game_id in &archived {
+            if let Some(game) = self.games.remove(game_id) {
+                let game_data = Self::lock_game(&game).clone();
+                self.archived_games.insert(*game_id, game_data);
+            }
+        }
+
+        archived
+    }
+
+    //The player gives up on a running game. The last player left wins it,
+    //otherwise the game goes on without them and they can't win it anymore
+    pub fn forfeit_game(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        let (game, context) = self.prepare_move(player_id, game_id)?;
+
+        let game_data = {
+            let mut game_data = Self::lock_game(&game);
+            let game_data = &mut *game_data;
+
+            if !game_data
+                .players
+                .iter()
+                .any(|(player, _)| player.id == player_id)
+            {
+                return Err(GameError::NotInGame);
+            }
+            Self::ensure_running(game_data)?;
+            if game_data.eliminated.contains(&player_id) {
+                return Err(GameError::PlayerEliminated);
+            }
+            if game_data.forfeited.contains(&player_id) {
+                return Err(GameError::PlayerForfeited);
+            }
+
+            game_data.forfeited.push(player_id);
+            Self::prune_stale_inputs(game_data);
+
+            if game_data.remaining_players().count() <= 1 {
+                game_data.status = GameStatus::Ended;
+                let winner = game_data.remaining_players().next().map(|player| player.id);
+                game_data.winner = winner;
+                game_data.winners = winner.into_iter().collect();
+                game_data.ended_at = Some(context.now);
+            } else if !game_data.current_round.inputs.is_empty()
+                && game_data.required_submissions_remaining() == 0
+            {
+                //Everyone else already played, the round was only waiting
+                //for them
+                Self::finish_round(game_data, &context);
+            }
+            game_data.version += 1;
+
+            game_data.clone()
+        };
+        self.release_players(&game_data);
+
+        Ok(game_data)
+    }
+
+    //Any player still in the game can pause it, moves are refused until one
+    //of them resumes it
+    pub fn pause_game(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.set_game_paused(player_id, game_id, true)
+    }
+
+    pub fn resume_game(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<GameData, GameError> {
+        self.set_game_paused(player_id, game_id, false)
+    }
+
+    //Pausing a paused game or resuming a running one changes nothing
+    fn set_game_paused(
+        &mut self,
+        player_id: PlayerId,
+        game_id: GameId,
+        paused: bool,
+    ) -> Result<GameData, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        let now = self.clock.now();
+        let game = self.game_handle(game_id)?;
+        let mut game_data = Self::lock_game(&game);
+        let game_data = &mut *game_data;
+
+        if !game_data
+            .players
+            .iter()
+            .any(|(player, _)| player.id == player_id)
+        {
+            return Err(GameError::NotInGame);
+        }
+        if game_data.eliminated.contains(&player_id) {
+            return Err(GameError::PlayerEliminated);
+        }
+        if game_data.forfeited.contains(&player_id) {
+            return Err(GameError::PlayerForfeited);
+        }
+
+        match (&game_data.status, paused) {
+            (GameStatus::Ended, _) => return Err(GameError::GameNotRunning),
+            (GameStatus::Running, true) => {
+                game_data.status = GameStatus::Paused;
+                game_data.paused_at = Some(now);
+                game_data.version += 1;
+            }
+            (GameStatus::Paused, false) => {
+                //The round timeout picks up where it stopped
+                if let Some(paused_at) = game_data.paused_at.take() {
+                    game_data.round_started_at += now.saturating_sub(paused_at);
+                }
+                game_data.status = GameStatus::Running;
+                game_data.version += 1;
+            }
+            _ => (),
+        }
+
+        Ok(game_data.clone())
+    }
+
+    //Players of a running game who weren't heard from for too long are
+    //taken as disconnected and forfeit it, instead of the game waiting for
+    //them forever. Bots are never heard from, they stay
+    pub fn forfeit_disconnected_players(&mut self) -> Vec<GameId> {
+        let disconnect_after_ms = self.config.disconnect_after_ms;
+        if disconnect_after_ms == 0 {
+            return vec![];
+        }
+
+        let now = self.clock.now();
+        let disconnected = self
+            .idle_games()
+            .filter(|game| game.status == GameStatus::Running)
+            .flat_map(|game| {
+                game.remaining_players()
+                    .filter(|player| {
+                        !self.is_bot(player.id)
+                            && self.last_seen.get(&player.id).is_some_and(|last_seen| {
+                                now.saturating_sub(*last_seen) >= disconnect_after_ms
+                            })
+                    })
+                    .map(|player| (game.id, player.id))
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        let mut forfeited = vec![];
+        for (game_id, player_id) in disconnected {
+            //An earlier forfeit may have ended the game already
+            if self.forfeit_game(player_id, game_id).is_ok() {
+                forfeited.push(game_id);
+            }
+        }
+
+        forfeited.sort();
+        forfeited.dedup();
+        forfeited
+    }
+
+    //Guests and bots go away with the game they played, once it is counted
+    //in the stats of the others
+    pub fn release_players(&mut self, game_data: &GameData) {
+        if game_data.status != GameStatus::Ended {
+            return;
+        }
+
+        self.record_game_stats(game_data);
+
+        let is_in_game = |player_id: PlayerId| {
+            game_data
+                .players
+                .iter()
+                .any(|(player, _)| player.id == player_id)
+        };
+        self.ephemeral_players
+            .retain(|ephemeral_id, _| !is_in_game(*ephemeral_id));
+        self.session_tokens
+            .retain(|player_id, _| !is_in_game(*player_id) || self.players.contains_key(player_id));
+        self.bots.retain(|(bot_id, _)| !is_in_game(*bot_id));
+    }
+
+    fn record_game_stats(&mut self, game_data: &GameData) {
+        for (player, _) in &game_data.players {
+            if !self.players.contains_key(&player.id) {
+                continue;
+            }
+
+            let stats = self.player_stats.entry(player.id).or_default();
+            stats.games_played += 1;
+            if !game_data.winners.contains(&player.id) {
+                stats.losses += 1;
+            } else if game_data.winners.len() == 1 {
+                stats.wins += 1;
+            } else {
+                stats.draws += 1;
+            }
+        }
+    }
+
+    pub fn player_stats(&self, player_id: PlayerId) -> Result<PlayerStats, GameError> {
+        self.find_player(player_id)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        Ok(self
+            .player_stats
+            .get(&player_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    //Resolves the rounds which went past their game's timeout, without the
+    //players who didn't act. Rounds nobody played in are left alone, there
+    //would be no one to award them to
+    pub fn expire_rounds(&mut self) -> Vec<GameId> {
+        let context = self.move_context();
+
+        let mut expired = vec![];
+        let mut ended = vec![];
+        for mut game_data in self.idle_games() {
+            let game_data = &mut *game_data;
+
+            let is_expired = game_data.status == GameStatus::Running
+                && !game_data.current_round.inputs.is_empty()
+                && game_data
+                    .settings
+                    .round_timeout_secs
+                    .is_some_and(|timeout| {
+                        context.now.saturating_sub(game_data.round_started_at) >= timeout * 1000
+                    });
+            if !is_expired {
+                continue;
+            }
+
+            Self::prune_stale_inputs(game_data);
+            game_data.current_round.timed_out = game_data
+                .remaining_players()
+                .filter(|player| !game_data.current_round.inputs.contains_key(&player.id))
+                .map(|player| player.id)
+                .collect();
+            Self::finish_round(game_data, &context);
+            game_data.version += 1;
+
+            expired.push(game_data.id);
+            if game_data.status == GameStatus::Ended {
+                ended.push(game_data.clone());
+            }
+        }
+
+        for game_data in &ended {
+            self.release_players(game_data);
+        }
+
+        expired.sort();
+        expired
+    }
+
+    //Plays several players' moves at once, e.g. for hotseat games on a single
+    //device. Either every move is applied or none of them
+    #[cfg(test)]
+    pub fn play_batch(
+        &mut self,
+        game_id: GameId,
+        moves: Vec<(PlayerId, ActionKind)>,
+    ) -> Result<GameData, GameError> {
+        let (game, context) = self.prepare_batch(game_id, &moves)?;
+        let result = Self::apply_batch(&game, &moves, &context);
+
+        self.settle_batch(&moves, result)
+    }
+
+    //Everything a batch of moves needs from the server, like prepare_move
+    pub fn prepare_batch(
+        &self,
+        game_id: GameId,
+        moves: &[(PlayerId, ActionKind)],
+    ) -> Result<(GameHandle, MoveContext), GameError> {
+        let game = self.game_handle(game_id)?;
+
+        if moves.is_empty() {
+            return Err(GameError::NoMoves);
+        }
+        for (player_id, _) in moves {
+            self.find_player(*player_id)
+                .ok_or(GameError::UnknownPlayer)?;
+        }
+
+        Ok((game, self.move_context()))
+    }
+
+    //The game stays locked for the whole batch, so no other move can slip in
+    //between two of its moves. A refused move comes back with the player who
+    //sent it, the batch must then be settled with settle_batch
+    pub fn apply_batch(
+        game: &GameHandle,
+        moves: &[(PlayerId, ActionKind)],
+        context: &MoveContext,
+    ) -> Result<(GameData, bool), (PlayerId, GameError)> {
+        let mut game_data = Self::lock_game(game);
+        let was_running = game_data.status != GameStatus::Ended;
+        let mut new_game_data = game_data.clone();
+        for (player_id, action) in moves {
+            Self::play_move(
+                &mut new_game_data,
+                *player_id,
+                action.clone(),
+                None,
+                None,
+                context,
+            )
+            .map_err(|error| (*player_id, error))?;
+        }
+        *game_data = new_game_data.clone();
+        let ended_game = was_running && new_game_data.status == GameStatus::Ended;
+
+        Ok((new_game_data, ended_game))
+    }
+
+    //Settles each move of the batch, only the refused one is counted when
+    //the batch didn't go through
+    pub fn settle_batch(
+        &mut self,
+        moves: &[(PlayerId, ActionKind)],
+        result: Result<(GameData, bool), (PlayerId, GameError)>,
+    ) -> Result<GameData, GameError> {
+        match result {
+            Ok((game_data, ended_game)) => {
+                let mut result = Ok(game_data.clone());
+                for (index, (player_id, _)) in moves.iter().enumerate() {
+                    //Only the last move can have ended the game
+                    let ended_game = ended_game && index + 1 == moves.len();
+                    result = self.settle_move(*player_id, Ok((game_data.clone(), ended_game)));
+                }
+                result
+            }
+            Err((player_id, error)) => self.settle_move(player_id, Err(error)),
+        }
+    }
+
+    fn ensure_running(game_data: &GameData) -> Result<(), GameError> {
+        match game_data.status {
+            GameStatus::Running => Ok(()),
+            GameStatus::Paused => Err(GameError::GamePaused),
+            GameStatus::Ended => Err(GameError::GameNotRunning),
+        }
+    }
+
+    fn validate_action(
+        game_data: &GameData,
+        player_id: PlayerId,
+        action: &ActionKind,
+        now: Timestamp,
+    ) -> Result<(), GameError> {
+        if !game_data
+            .players
+            .iter()
+            .any(|(player, _)| player.id == player_id)
+        {
+            return Err(GameError::NotInGame);
+        }
+
+        Self::ensure_running(game_data)?;
+
+        if game_data.eliminated.contains(&player_id) {
+            return Err(GameError::PlayerEliminated);
+        }
+
+        if game_data.forfeited.contains(&player_id) {
+            return Err(GameError::PlayerForfeited);
+        }
+
+        if !game_data.settings.kind.logic().is_action_valid(action) {
+            return Err(GameError::ActionNotAvailable);
+        }
+
+        if let ActionKind::Move { from, to } = action {
+            Self::validate_chess_move(game_data, player_id, *from, *to)?;
+        }
+
+        if !game_data.settings.allow_action_change
+            && game_data.current_round.inputs.contains_key(&player_id)
+        {
+            return Err(GameError::ActionLockedIn);
+        }
+
+        if let (Some(cooldown), Some(submitted_at)) = (
+            game_data.settings.action_cooldown_ms,
+            game_data.current_round.submitted_at.get(&player_id),
+        ) {
+            if now.saturating_sub(*submitted_at) < cooldown {
+                return Err(GameError::ActionCooldown);
+            }
+        }
+
+        Ok(())
+    }
+
+    //The first player has the white pieces and moves first in each round.
+    //Moves are applied to the board as soon as they are played, so they
+    //can't be changed afterwards
+    fn validate_chess_move(
+        game_data: &GameData,
+        player_id: PlayerId,
+        from: chess::Square,
+        to: chess::Square,
+    ) -> Result<(), GameError> {
+        let board = game_data
+            .board
+            .as_ref()
+            .ok_or(GameError::ActionNotAvailable)?;
+
+        if game_data.current_round.inputs.contains_key(&player_id) {
+            return Err(GameError::ActionLockedIn);
+        }
+
+        let (white, _) = &game_data.players[0];
+        let color = if white.id == player_id {
+            ChessColor::White
+        } else if game_data.current_round.inputs.contains_key(&white.id) {
+            ChessColor::Black
+        } else {
+            return Err(GameError::NotYourTurn);
+        };
+
+        if !board.is_legal_move(color, from, to) {
+            return Err(GameError::IllegalMove);
+        }
+
+        Ok(())
+    }
+
+    //Inputs left by players who are not part of the game anymore would block
+    //or skew the resolution of the round
+    fn prune_stale_inputs(game_data: &mut GameData) {
+        let GameData {
+            players,
+            current_round,
+            eliminated,
+            forfeited,
+            ..
+        } = game_data;
+
+        let is_in_game = |player_id: &PlayerId| {
+            players.iter().any(|(player, _)| player.id == *player_id)
+                && !eliminated.contains(player_id)
+                && !forfeited.contains(player_id)
+        };
+
+        current_round
+            .inputs
+            .retain(|input_player_id, _| is_in_game(input_player_id));
+        current_round
+            .submitted_at
+            .retain(|input_player_id, _| is_in_game(input_player_id));
+    }
+
+    fn resolve_current_round(game_data: &mut GameData) {
+        let scores_before = game_data
+            .players
+            .iter()
+            .map(|(player, score)| (player.id, *score))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut round_results = match game_data.settings.scoring_mode {
+            ScoringMode::Pairwise => Self::resolve_pairwise(game_data),
+            ScoringMode::Majority => Self::resolve_majority(game_data),
+            ScoringMode::Survival => Self::resolve_survival(game_data),
+        };
+        round_results.extend(Self::resolve_forfeits(game_data));
+
+        game_data.current_round.per_player = Self::round_outcomes(game_data, &round_results);
+        for player_id in &game_data.current_round.timed_out {
+            game_data
+                .current_round
+                .per_player
+                .insert(*player_id, PlayerRoundOutcome::Lost);
+        }
+        game_data.current_round.result = Some(round_results);
+        game_data.current_round.score_deltas = game_data
+            .players
+            .iter()
+            .map(|(player, score)| (player.id, *score as i64 - scores_before[&player.id] as i64))
+            .collect();
+        //Players submitting at the same time are revealed by id
+        game_data.current_round.reveal_order = game_data
+            .current_round
+            .inputs
+            .keys()
+            .copied()
+            .sorted_by_key(|player_id| {
+                (
+                    game_data.current_round.submitted_at.get(player_id),
+                    *player_id,
+                )
+            })
+            .collect();
+
+        game_data
+            .round_history
+            .push(game_data.current_round.clone());
+        game_data.current_round = RoundData::default();
+    }
+
+    //Players who timed out lose against everyone who played, or are knocked
+    //out of Survival games. In Majority games they just don't score
+    fn resolve_forfeits(game_data: &mut GameData) -> Vec<RoundResult> {
+        let timed_out = game_data.current_round.timed_out.clone();
+
+        match game_data.settings.scoring_mode {
+            ScoringMode::Pairwise => {
+                let duels = game_data
+                    .current_round
+                    .inputs
+                    .keys()
+                    .cartesian_product(timed_out.iter())
+                    .map(|(winner_id, loser_id)| {
+                        (
+                            [*winner_id.min(loser_id), *winner_id.max(loser_id)],
+                            *winner_id,
+                        )
+                    })
+                    .sorted()
+                    .map(|(players, winner_id)| RoundResult::Duel {
+                        players,
+                        winner: Some(winner_id),
+                    })
+                    .collect_vec();
+
+                for (player_data, score) in game_data.players.iter_mut() {
+                    if game_data.current_round.inputs.contains_key(&player_data.id) {
+                        *score += timed_out.len();
+                    }
+                }
+
+                duels
+            }
+            ScoringMode::Majority => vec![],
+            ScoringMode::Survival => {
+                game_data.eliminated.extend(timed_out.iter().copied());
+                timed_out
+                    .into_iter()
+                    .map(RoundResult::Eliminated)
+                    .collect_vec()
+            }
+        }
+    }
+
+    fn round_outcomes(
+        game_data: &GameData,
+        round_results: &[RoundResult],
+    ) -> BTreeMap<PlayerId, PlayerRoundOutcome> {
+        let inputs = &game_data.current_round.inputs;
+        let kind = &game_data.settings.kind;
+
+        if round_results == [RoundResult::Draw] {
+            return inputs
+                .keys()
+                .map(|player_id| (*player_id, PlayerRoundOutcome::Drew))
+                .collect();
+        }
+
+        inputs
+            .iter()
+            .map(|(player_id, action)| {
+                let outcome = match game_data.settings.scoring_mode {
+                    //Every other player was faced, the outcome is only clear
+                    //cut when all the duels went the same way
+                    ScoringMode::Pairwise => {
+                        let duels = inputs
+                            .iter()
+                            .filter(|(other_id, _)| *other_id != player_id)
+                            .map(|(_, other_action)| {
+                                if kind.beats(action, other_action) {
+                                    PlayerRoundOutcome::Won
+                                } else if kind.beats(other_action, action) {
+                                    PlayerRoundOutcome::Lost
+                                } else {
+                                    PlayerRoundOutcome::Drew
+                                }
+                            })
+                            .chain(
+                                game_data
+                                    .current_round
+                                    .timed_out
+                                    .iter()
+                                    .map(|_| PlayerRoundOutcome::Won),
+                            )
+                            .unique()
+                            .collect_vec();
+
+                        match duels.as_slice() {
+                            [outcome] => outcome.clone(),
+                            _ => PlayerRoundOutcome::Mixed,
+                        }
+                    }
+                    ScoringMode::Majority => {
+                        if round_results.contains(&RoundResult::Winner(*player_id)) {
+                            PlayerRoundOutcome::Won
+                        } else {
+                            PlayerRoundOutcome::Lost
+                        }
+                    }
+                    ScoringMode::Survival => {
+                        if round_results.contains(&RoundResult::Eliminated(*player_id)) {
+                            PlayerRoundOutcome::Lost
+                        } else {
+                            PlayerRoundOutcome::Won
+                        }
+                    }
+                };
+
+                (*player_id, outcome)
+            })
+            .collect()
+    }
+
+    fn resolve_pairwise(game_data: &mut GameData) -> Vec<RoundResult> {
+        let round_results = game_data
+            .settings
+            .kind
+            .logic()
+            .resolve_round(&game_data.current_round.inputs);
+
+        for round_result in &round_results {
+            if let RoundResult::Duel {
+                winner: Some(winner_id),
+                ..
+            } = round_result
+            {
+                game_data
+                    .players
+                    .iter_mut()
+                    .filter(|(player_data, _)| player_data.id == *winner_id)
+                    .for_each(|(_, score)| *score += 1);
+            }
+        }
+
+        round_results
+    }
+
+    //Actions thrown this round which beat at least one other thrown action
+    fn winning_actions(
+        kind: &GameKind,
+        inputs: &BTreeMap<PlayerId, ActionKind>,
+    ) -> Vec<ActionKind> {
+        let thrown = inputs.values().unique().collect_vec();
+
+        thrown
+            .iter()
+            .filter(|action| thrown.iter().any(|other| kind.beats(action, other)))
+            .map(|action| (*action).clone())
+            .collect_vec()
+    }
+
+    fn resolve_majority(game_data: &mut GameData) -> Vec<RoundResult> {
+        let inputs = &game_data.current_round.inputs;
+
+        let throwers_count =
+            |action: &ActionKind| inputs.values().filter(|thrown| *thrown == action).count();
+
+        //Only a single most played winning action scores, a tie is a draw
+        let mut counts = Self::winning_actions(&game_data.settings.kind, inputs)
+            .into_iter()
+            .map(|action| (throwers_count(&action), action))
+            .sorted_by(|(a_count, _), (b_count, _)| b_count.cmp(a_count));
+        let majority_action = match (counts.next(), counts.next()) {
+            (Some((first, action)), Some((second, _))) if first > second => action,
+            (Some((_, action)), None) => action,
+            _ => return vec![RoundResult::Draw],
+        };
+
+        let winners = inputs
+            .iter()
+            .filter(|(_, action)| **action == majority_action)
+            .map(|(player_id, _)| *player_id)
+            .collect_vec();
+
+        game_data
+            .players
+            .iter_mut()
+            .filter(|(player_data, _)| winners.contains(&player_data.id))
+            .for_each(|(_, score)| *score += 1);
+
+        winners.into_iter().map(RoundResult::Winner).collect_vec()
+    }
+
+    fn resolve_survival(game_data: &mut GameData) -> Vec<RoundResult> {
+        let inputs = &game_data.current_round.inputs;
+        let kind = &game_data.settings.kind;
+        let winning_actions = Self::winning_actions(kind, inputs);
+
+        //A player is out when their action is beaten and beats nothing, so
+        //a round where every action was thrown eliminates nobody
+        let losers = inputs
+            .iter()
+            .filter(|(_, action)| {
+                !winning_actions.contains(action)
+                    && winning_actions
+                        .iter()
+                        .any(|winner| kind.beats(winner, action))
+            })
+            .map(|(player_id, _)| *player_id)
+            .collect_vec();
+
+        if losers.is_empty() {
+            return vec![RoundResult::Draw];
+        }
+
+        game_data.eliminated.extend(losers.iter().copied());
+        losers
+            .into_iter()
+            .map(RoundResult::Eliminated)
+            .collect_vec()
+    }
+
+    fn update_game_status(game_data: &mut GameData) {
+        if game_data.settings.scoring_mode == ScoringMode::Survival
+            && game_data.remaining_players().count() <= 1
+        {
+            game_data.status = GameStatus::Ended;
+        }
+
+        //Sudden death rounds go past the end condition, so it is reached
+        //rather than hit exactly
+        match game_data.settings.end_condition {
+            EndCondition::TotalRounds(x) => {
+                if game_data.round_history.len() >= x {
+                    game_data.status = GameStatus::Ended;
+                }
+            }
+            EndCondition::FirstToScore(x) => {
+                if let Some((_, max)) = game_data
+                    .players
+                    .iter()
+                    .max_by(|(_, a_score), (_, b_score)| a_score.cmp(b_score))
+                {
+                    if *max >= x {
+                        game_data.status = GameStatus::Ended;
+                    }
+                }
+            }
+        }
+
+        //A tied game keeps going one round at a time until a round breaks the
+        //tie, or the replay rounds run out. Survival games end when nobody is
+        //left to replay the round
+        if let TieBreak::SuddenDeath(max_rounds) = game_data.settings.tie_break {
+            if game_data.status == GameStatus::Ended
+                && game_data.settings.scoring_mode != ScoringMode::Survival
+                && game_data.winner().is_none()
+                && game_data.tie_break_rounds < max_rounds
+            {
+                game_data.status = GameStatus::Running;
+                game_data.tie_break_rounds += 1;
+            }
+        }
+
+        if game_data.round_history.len() >= game_data.settings.max_rounds {
+            game_data.status = GameStatus::Ended;
+        }
+
+        if game_data.status == GameStatus::Ended {
+            game_data.winner = game_data.winner();
+            game_data.winners = game_data.leaders();
+        }
+    }
+
+    pub fn get_legal_actions(
+        &self,
+        player_id: PlayerId,
+        game_id: GameId,
+    ) -> Result<Vec<ActionKind>, GameError> {
+        let game_data = self.get_game_data(player_id, game_id)?;
+
+        if game_data.status != GameStatus::Running
+            || game_data.eliminated.contains(&player_id)
+            || game_data.forfeited.contains(&player_id)
+        {
+            return Ok(vec![]);
+        }
+
+        if !game_data.settings.allow_action_change
+            && game_data.current_round.inputs.contains_key(&player_id)
+        {
+            return Ok(vec![]);
+        }
+
+        Ok(game_data.settings.kind.available_actions())
+    }
+
+    pub fn set_room_visibility(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        visibility: RoomVisibility,
+    ) -> Result<RoomData, GameError> {
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if room_data.host_id != player_id {
+            return Err(GameError::NotHost);
+        }
+
+        room_data.visibility = visibility;
+
+        Ok(room_data.clone())
+    }
+
+    pub fn set_room_password(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        password: Option<String>,
+    ) -> Result<RoomData, GameError> {
+        let room_data = self.rooms.get_mut(&room_id).ok_or(GameError::UnknownRoom)?;
+
+        if room_data.host_id != player_id {
+            return Err(GameError::NotHost);
+        }
+
+        room_data.password = password;
+
+        Ok(room_data.clone())
+    }
+
+    //Rooms the given player is allowed to see, anonymous lookups only get
+    //the public ones
+    pub fn get_rooms_filtered(&mut self, player_id: Option<PlayerId>) -> Vec<RoomData> {
+        let rooms = self.get_rooms_list();
+
+        rooms
+            .into_iter()
+            .filter(|room| match room.visibility {
+                RoomVisibility::Public => true,
+                RoomVisibility::FriendsOnly => {
+                    let Some(player_id) = player_id else {
+                        return false;
+                    };
+
+                    //The friends list of the host is looked up on the server
+                    //side, the copy kept in the room may be outdated
+                    room.host_id == player_id
+                        || self
+                            .players
+                            .get(&room.host_id)
+                            .is_some_and(|host| host.friends.contains(&player_id))
+                }
+            })
+            .collect_vec()
+    }
+
+    pub fn get_rooms_list(&mut self) -> Vec<RoomData> {
+        //leave_room already deletes rooms once empty, this only cleans up
+        //rooms that would have been emptied some other way
+        self.rooms.retain(|_, room| !room.players.is_empty());
+
+        self.rooms
+            .values()
+            .cloned()
+            .sorted_by_key(|room| room.id)
+            .collect_vec()
+    }
+
+    //Live and archived games, ordered by id. Anyone can list them, a game
+    //busy with a move is left out rather than waited on
+    pub fn get_games_list(&self) -> Vec<GameData> {
+        self.idle_and_archived_games()
+            .map(|game| (*game).clone())
+            .sorted_by_key(|game| game.id)
+            .collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn test_create_player() {
+        let mut server_data = ServerData::default();
+
+        assert_eq!(
+            server_data
+                .create_player_with_region("Alice".to_string(), None)
+                .unwrap(),
+            PlayerData {
+                id: 0,
+                name: "Alice".to_string(),
+                region: None,
+                friends: vec![],
+            }
+        );
+
+        assert_eq!(
+            server_data
+                .create_player_with_region("Bob".to_string(), None)
+                .unwrap(),
+            PlayerData {
+                id: 1,
+                name: "Bob".to_string(),
+                region: None,
+                friends: vec![],
+            }
+        );
+
+        assert!(
+            server_data
+                .create_player_with_region("Bob".to_string(), None)
+                .is_err(),
+            "Bob already exists"
+        );
+    }
+
+    #[test]
+    fn test_join_room_became_full() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    player_count: 3,
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+
+        //bob joins, the room still has a free seat
+        let (_, became_full) = server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        assert!(!became_full);
+
+        //charlie takes the last seat
+        let (_, became_full) = server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .unwrap();
+        assert!(became_full);
+    }
+
+    #[test]
+    fn test_legal_actions_empty_after_lock() {
+        let mut server_data = ServerData::default();
+
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                allow_action_change: false,
+                ..GameSettings::default()
+            },
+        );
+        let (alice, bob) = (players[0].id, players[1].id);
+
+        //every action is available on an open round
+        assert_eq!(
+            server_data.get_legal_actions(alice, game_data.id).unwrap(),
+            vec![ActionKind::Rock, ActionKind::Paper, ActionKind::Scissors]
+        );
+
+        //once alice locked in, she has nothing left to play this round
+        server_data
+            .play_round(alice, game_data.id, ActionKind::Rock)
+            .unwrap();
+        assert!(server_data
+            .get_legal_actions(alice, game_data.id)
+            .unwrap()
+            .is_empty());
+
+        //and changing her mind is rejected
+        assert!(server_data
+            .play_round(alice, game_data.id, ActionKind::Paper)
+            .is_err());
+
+        //bob still has every action available
+        assert_eq!(
+            server_data
+                .get_legal_actions(bob, game_data.id)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_rejoin_grace_period() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        server_data.config.rejoin_grace_ms = 1000;
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
+
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+
+        //bob leaves by mistake, his seat is kept for him
+        server_data.leave_room(bob.id, room_data.id).unwrap();
+        assert!(server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .is_err());
+
+        //bob comes back within the grace period
+        server_data.clock.advance(500);
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+
+        //bob leaves again and does not come back in time
+        server_data.leave_room(bob.id, room_data.id).unwrap();
+        server_data.clock.advance(1000);
+
+        //his seat is given away to charlie, bob can't join anymore
+        server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .unwrap();
+        assert!(server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rejected_action_leaves_game_untouched() {
+        let mut server_data = ServerData::default();
+
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                allow_action_change: false,
+                ..GameSettings::default()
+            },
+        );
+        let alice = players[0].id;
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap()
+            .id;
+
+        server_data
+            .play_round(alice, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        let before = format!("{:?}", server_data.games);
+
+        //alice already locked in, charlie is not part of the game
+        assert!(server_data
+            .play_round(alice, game_data.id, ActionKind::Paper)
+            .is_err());
+        assert!(server_data
+            .play_round(charlie, game_data.id, ActionKind::Paper)
+            .is_err());
+
+        assert_eq!(before, format!("{:?}", server_data.games));
+    }
+
+    fn assert_unique_players(server_data: &ServerData) {
+        for room_data in server_data.rooms.values() {
+            let player_ids = room_data
+                .players
+                .iter()
+                .map(|player| player.id)
+                .collect_vec();
+            assert_eq!(
+                player_ids.len(),
+                player_ids.iter().unique().count(),
+                "a player is seated twice in room {}",
+                room_data.id
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_player_seated_twice() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    player_count: 4,
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+        assert_unique_players(&server_data);
+
+        //the host can't take a second seat
+        assert!(server_data
+            .join_room_with_password(alice.id, room_data.id, None)
+            .is_err());
+        assert_unique_players(&server_data);
+
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        assert!(server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .is_err());
+        assert_unique_players(&server_data);
+
+        //leaving and coming back still leaves a single seat
+        server_data.leave_room(bob.id, room_data.id).unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        assert_unique_players(&server_data);
+
+        //the guard itself rejects a duplicate whatever the caller
+        let room_data = server_data.rooms.get_mut(&room_data.id).unwrap();
+        assert!(ServerData::seat_player(room_data, bob.clone()).is_err());
+        assert_unique_players(&server_data);
+    }
+
+    #[test]
+    fn test_play_batch_resolves_round() {
+        let mut server_data = ServerData::default();
+
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+        let (alice, bob) = (players[0].id, players[1].id);
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap()
+            .id;
+
+        //a batch with a player outside the game is rejected as a whole
+        assert!(server_data
+            .play_batch(
+                game_data.id,
+                vec![(alice, ActionKind::Rock), (charlie, ActionKind::Paper)]
+            )
+            .is_err());
+        assert!(ServerData::lock_game(&server_data.games[&game_data.id])
+            .current_round
+            .inputs
+            .is_empty());
+        //only the refused move is counted
+        assert_eq!(server_data.move_stats[&charlie].rejected, 1);
+        assert!(!server_data.move_stats.contains_key(&alice));
+
+        let game_data = server_data
+            .play_batch(
+                game_data.id,
+                vec![(alice, ActionKind::Rock), (bob, ActionKind::Scissors)],
+            )
+            .unwrap();
+        assert_eq!(server_data.move_stats[&alice].accepted, 1);
+        assert_eq!(server_data.move_stats[&bob].accepted, 1);
+
+        assert_eq!(game_data.round_history.len(), 1);
+        assert_eq!(
+            game_data.round_history[0].result,
+            Some(vec![RoundResult::Duel {
+                players: [alice, bob],
+                winner: Some(alice)
+            }])
+        );
+        assert_eq!(game_data.players[0].1, 1);
+        assert!(game_data.current_round.inputs.is_empty());
+    }
+
+    #[test]
+    fn test_total_rounds_winner() {
+        let mut server_data = ServerData::default();
+
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                end_condition: EndCondition::TotalRounds(3),
+                ..GameSettings::default()
+            },
+        );
+        let (alice, bob) = (players[0].id, players[1].id);
+
+        //bob wins two rounds, the last one is a draw
+        for (alice_action, bob_action) in [
+            (ActionKind::Rock, ActionKind::Paper),
+            (ActionKind::Scissors, ActionKind::Rock),
+            (ActionKind::Paper, ActionKind::Paper),
+        ] {
+            assert_eq!(
+                ServerData::lock_game(&server_data.games[&game_data.id]).winner,
+                None
+            );
+            server_data
+                .play_batch(game_data.id, vec![(alice, alice_action), (bob, bob_action)])
+                .unwrap();
+        }
+
+        let game_data = server_data.get_game_data(alice, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, Some(bob));
+    }
+
+    #[test]
+    fn test_stale_inputs_are_pruned() {
+        let mut server_data = ServerData::default();
+
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+        let (alice, bob) = (players[0].id, players[1].id);
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap()
+            .id;
+
+        //charlie is not part of the game but somehow left an input behind
+        ServerData::lock_game(&server_data.games[&game_data.id])
+            .current_round
+            .inputs
+            .insert(charlie, ActionKind::Paper);
+
+        let game_data = server_data
+            .play_round(alice, game_data.id, ActionKind::Rock)
+            .unwrap();
+        assert!(!game_data.current_round.inputs.contains_key(&charlie));
+
+        let game_data = server_data
+            .play_round(bob, game_data.id, ActionKind::Scissors)
+            .unwrap();
+
+        //only the alice/bob pair got resolved
+        assert_eq!(
+            game_data.round_history[0].result,
+            Some(vec![RoundResult::Duel {
+                players: [alice, bob],
+                winner: Some(alice)
+            }])
+        );
+        assert!(!game_data.round_history[0].inputs.contains_key(&charlie));
+    }
+
+    #[test]
+    fn test_empty_rooms_are_hidden_and_removed() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+
+        server_data
+            .create_room(alice.id, "alice's room".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(bob.id, "bob's room".to_string(), None)
+            .unwrap();
+
+        server_data
+            .rooms
+            .get_mut(&room_data.id)
+            .unwrap()
+            .players
+            .clear();
+
+        let rooms_list = server_data.get_rooms_list();
+        assert_eq!(rooms_list.len(), 1);
+        assert_eq!(rooms_list[0].name, "alice's room");
+
+        assert!(!server_data.rooms.contains_key(&room_data.id));
+    }
+
+    #[test]
+    fn test_merge_rooms() {
+        let mut server_data = ServerData::default();
+
+        let players = ["Alice", "Bob", "Charlie", "Dave"]
+            .into_iter()
+            .map(|name| {
+                server_data
+                    .create_player_with_region(name.to_string(), None)
+                    .unwrap()
+            })
+            .collect_vec();
+
+        let settings = GameSettings {
+            player_count: 4,
+            ..GameSettings::default()
+        };
+
+        let first_room = server_data
+            .create_room(
+                players[0].id,
+                "first room".to_string(),
+                Some(settings.clone()),
+            )
+            .unwrap();
+        server_data
+            .join_room_with_password(players[1].id, first_room.id, None)
+            .unwrap();
+
+        let second_room = server_data
+            .create_room(players[2].id, "second room".to_string(), Some(settings))
+            .unwrap();
+        server_data
+            .join_room_with_password(players[3].id, second_room.id, None)
+            .unwrap();
+
+        //a room that would overflow can't be merged
+        let eve = server_data
+            .create_player_with_region("Eve".to_string(), None)
+            .unwrap();
+        let third_room = server_data
+            .create_room(eve.id, "third room".to_string(), None)
+            .unwrap();
+        assert!(server_data
+            .merge_rooms(first_room.id, third_room.id)
+            .is_err());
+
+        let merged_room = server_data
+            .merge_rooms(second_room.id, first_room.id)
+            .unwrap();
+        assert_eq!(merged_room.players.len(), 4);
+        assert!(!server_data.rooms.contains_key(&second_room.id));
+
+        //the host of the target room can launch the merged room once everyone
+        //confirmed they are ready again
+        for player in &players {
+            server_data
+                .set_ready(player.id, first_room.id, true)
+                .unwrap();
+        }
+        let game_data = server_data
+            .launch_room(players[0].id, first_room.id)
+            .unwrap();
+        assert_eq!(game_data.players.len(), 4);
+    }
+
+    #[test]
+    fn test_action_cooldown() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                action_cooldown_ms: Some(1000),
+                ..GameSettings::default()
+            },
+        );
+        let alice = players[0].id;
+
+        server_data
+            .play_round(alice, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        //too soon
+        server_data.clock.advance(999);
+        assert!(server_data
+            .play_round(alice, game_data.id, ActionKind::Paper)
+            .is_err());
+
+        server_data.clock.advance(1);
+        let game_data = server_data
+            .play_round(alice, game_data.id, ActionKind::Paper)
+            .unwrap();
+        assert_eq!(
+            game_data.current_round.inputs.get(&alice),
+            Some(&ActionKind::Paper)
+        );
+    }
+
+    #[test]
+    fn test_launch_readiness() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
+
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::NotFull {
+                players: 1,
+                required: 2,
+                recovery: vec![LaunchRecovery::FillWithBots { bots: 1 }],
+            }
+        );
+        assert_eq!(
+            server_data.launch_readiness(bob.id, room_data.id),
+            LaunchReadiness::NotInRoom
+        );
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id + 1),
+            LaunchReadiness::UnknownRoom
+        );
+
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+
+        assert_eq!(
+            server_data.launch_readiness(bob.id, room_data.id),
+            LaunchReadiness::NotHost
+        );
+        assert_eq!(
+            server_data.launch_readiness(charlie.id, room_data.id),
+            LaunchReadiness::NotInRoom
+        );
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::NotReady { waiting: 1 }
+        );
+
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::Ready
+        );
+    }
+
+    #[test]
+    fn test_ready_check() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        let settings = GameSettings {
+            player_count: 3,
+            ..GameSettings::default()
+        };
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), Some(settings.clone()))
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+
+        assert_eq!(
+            server_data
+                .set_ready(charlie.id, room_data.id, true)
+                .unwrap_err(),
+            GameError::NotInRoom
+        );
+        let room_data = server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        assert_eq!(room_data.ready, vec![bob.id]);
+
+        //a new player resets everyone
+        let (room_data, _) = server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .unwrap();
+        assert!(room_data.ready.is_empty());
+        assert_eq!(
+            server_data.launch_room(alice.id, room_data.id).err(),
+            Some(GameError::PlayersNotReady)
+        );
+
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        server_data
+            .set_ready(charlie.id, room_data.id, true)
+            .unwrap();
+        server_data.set_ready(bob.id, room_data.id, false).unwrap();
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::NotReady { waiting: 1 }
+        );
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        server_data.launch_room(alice.id, room_data.id).unwrap();
+
+        //bots don't have to confirm, the players do before bots are seated
+        let room_data = server_data
+            .create_room(alice.id, "bot room".to_string(), Some(settings))
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        assert_eq!(
+            server_data
+                .launch_room_with_bots(alice.id, room_data.id, BotStrategy::default())
+                .err(),
+            Some(GameError::PlayersNotReady)
+        );
+        assert_eq!(server_data.rooms[&room_data.id].players.len(), 2);
+
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        let game_data = server_data
+            .launch_room_with_bots(alice.id, room_data.id, BotStrategy::default())
+            .unwrap();
+        assert_eq!(game_data.players.len(), 3);
+    }
+
+    #[test]
+    fn test_launch_recovery() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    player_count: 4,
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::NotFull {
+                players: 2,
+                required: 4,
+                recovery: vec![
+                    LaunchRecovery::FillWithBots { bots: 2 },
+                    LaunchRecovery::ReduceCount { player_count: 2 },
+                ],
+            }
+        );
+
+        assert_eq!(
+            server_data
+                .reduce_player_count(bob.id, room_data.id)
+                .unwrap_err(),
+            GameError::NotHost
+        );
+        server_data
+            .reduce_player_count(alice.id, room_data.id)
+            .unwrap();
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::NotReady { waiting: 1 }
+        );
+        server_data.set_ready(alice.id, room_data.id, true).unwrap();
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        assert_eq!(
+            server_data.launch_readiness(alice.id, room_data.id),
+            LaunchReadiness::Ready
+        );
+    }
+
+    #[test]
+    fn test_matchmaking_prefers_same_region() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        server_data.config.cross_region_wait_ms = 1000;
+
+        let eu_player = |server_data: &mut ServerData, name: &str| {
+            server_data
+                .create_player_with_region(name.to_string(), Some("eu".to_string()))
+                .unwrap()
+        };
+
+        let alice = eu_player(&mut server_data, "Alice");
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), Some("us".to_string()))
+            .unwrap();
+        let charlie = eu_player(&mut server_data, "Charlie");
+        let dave = eu_player(&mut server_data, "Dave");
+
+        //bob is from another region, alice keeps waiting
+        assert!(server_data.matchmake(bob.id).unwrap().is_none());
+        assert!(server_data.matchmake(alice.id).unwrap().is_none());
+
+        //charlie comes from the same region as alice
+        let game_data = server_data.matchmake(charlie.id).unwrap().unwrap();
+        let player_ids = game_data
+            .players
+            .iter()
+            .map(|(player, _)| player.id)
+            .collect_vec();
+        assert_eq!(player_ids, vec![alice.id, charlie.id]);
+
+        //bob has now waited long enough to be matched with anyone
+        server_data.clock.advance(1000);
+        let game_data = server_data.matchmake(dave.id).unwrap().unwrap();
+        let player_ids = game_data
+            .players
+            .iter()
+            .map(|(player, _)| player.id)
+            .collect_vec();
+        assert_eq!(player_ids, vec![bob.id, dave.id]);
+        assert!(server_data.matchmaking_queue.is_empty());
+    }
+
+    #[test]
+    fn test_estimate_wait() {
+        let mut sparse = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let lonely_alice = sparse
+            .create_player_with_region("Alice".to_string(), Some("eu".to_string()))
+            .unwrap();
+        assert!(sparse.matchmake(lonely_alice.id).unwrap().is_none());
+
+        let mut crowded = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        for (name, region) in [("Bob", "us"), ("Charlie", "asia"), ("Dave", "sa")] {
+            let player_data = crowded
+                .create_player_with_region(name.to_string(), Some(region.to_string()))
+                .unwrap();
+            assert!(crowded.matchmake(player_data.id).unwrap().is_none());
+        }
+        crowded
+            .clock
+            .advance(crowded.config.cross_region_wait_ms / 2);
+        let alice = crowded
+            .create_player_with_region("Alice".to_string(), Some("eu".to_string()))
+            .unwrap();
+        assert!(crowded.matchmake(alice.id).unwrap().is_none());
+
+        assert!(
+            crowded.estimate_wait(alice.id).unwrap()
+                < sparse.estimate_wait(lonely_alice.id).unwrap()
+        );
+        assert_eq!(
+            crowded.estimate_wait(alice.id).unwrap(),
+            crowded.config.cross_region_wait_ms / 2
+        );
+    }
+
+    #[test]
+    fn test_spectate_game() {
+        let mut server_data = ServerData::default();
+
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+        let alice = players[0].id;
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+
+        assert!(server_data.spectate_game(alice, game_data.id).is_err());
+
+        //spectating twice only lists the observer once
+        server_data.spectate_game(charlie.id, game_data.id).unwrap();
+        let game_data = server_data.spectate_game(charlie.id, game_data.id).unwrap();
+        assert_eq!(game_data.observers, vec![charlie]);
+    }
+
+    fn launch_game(
+        server_data: &mut ServerData,
+        names: &[&str],
+        settings: GameSettings,
+    ) -> (Vec<PlayerData>, GameData) {
+        let players = names
+            .iter()
+            .map(|name| {
+                server_data
+                    .create_player_with_region(name.to_string(), None)
+                    .unwrap()
+            })
+            .collect_vec();
+
+        let room_data = server_data
+            .create_room(players[0].id, "test room".to_string(), Some(settings))
+            .unwrap();
+        for player in &players[1..] {
+            server_data
+                .join_room_with_password(player.id, room_data.id, None)
+                .unwrap();
+        }
+        for player in &players {
+            server_data
+                .set_ready(player.id, room_data.id, true)
+                .unwrap();
+        }
+        let game_data = server_data
+            .launch_room(players[0].id, room_data.id)
+            .unwrap();
+
+        (players, game_data)
+    }
+
+    #[test]
+    fn test_majority_scoring() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie", "Dave", "Eve", "Frank"],
+            GameSettings {
+                player_count: 6,
+                end_condition: EndCondition::TotalRounds(3),
+                scoring_mode: ScoringMode::Majority,
+                ..GameSettings::default()
+            },
+        );
+
+        let mut play = |actions: [ActionKind; 6]| {
+            for (player, action) in players.iter().zip(actions) {
+                server_data
+                    .play_round(player.id, game_data.id, action)
+                    .unwrap();
+            }
+            server_data
+                .get_game_data(players[0].id, game_data.id)
+                .unwrap()
+        };
+
+        //3 Rock, 2 Paper, 1 Scissors: every action beats another one, Rock
+        //is the most played so its throwers score
+        let game_data = play([
+            ActionKind::Rock,
+            ActionKind::Rock,
+            ActionKind::Rock,
+            ActionKind::Paper,
+            ActionKind::Paper,
+            ActionKind::Scissors,
+        ]);
+        assert_eq!(
+            game_data.round_history[0].result,
+            Some(vec![
+                RoundResult::Winner(players[0].id),
+                RoundResult::Winner(players[1].id),
+                RoundResult::Winner(players[2].id),
+            ])
+        );
+        assert_eq!(
+            game_data
+                .players
+                .iter()
+                .map(|(_, score)| *score)
+                .collect_vec(),
+            vec![1, 1, 1, 0, 0, 0]
+        );
+
+        //an even 3-way split has no majority
+        let game_data = play([
+            ActionKind::Rock,
+            ActionKind::Rock,
+            ActionKind::Paper,
+            ActionKind::Paper,
+            ActionKind::Scissors,
+            ActionKind::Scissors,
+        ]);
+        assert_eq!(
+            game_data.round_history[1].result,
+            Some(vec![RoundResult::Draw])
+        );
+    }
+
+    #[test]
+    fn test_survival_eliminates_losers() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                end_condition: EndCondition::TotalRounds(3),
+                scoring_mode: ScoringMode::Survival,
+                ..GameSettings::default()
+            },
+        );
+
+        for (player, action) in
+            players
+                .iter()
+                .zip([ActionKind::Rock, ActionKind::Rock, ActionKind::Scissors])
+        {
+            server_data
+                .play_round(player.id, game_data.id, action)
+                .unwrap();
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(
+            game_data.round_history[0].result,
+            Some(vec![RoundResult::Eliminated(players[2].id)])
+        );
+    }
+
+    #[test]
+    fn test_survival_until_last_player() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie", "Dave"],
+            GameSettings {
+                player_count: 4,
+                end_condition: EndCondition::TotalRounds(10),
+                scoring_mode: ScoringMode::Survival,
+                ..GameSettings::default()
+            },
+        );
+
+        //the last player still standing throws Scissors and is knocked out
+        for round in 0..3 {
+            let remaining = &players[..4 - round];
+            for (index, player) in remaining.iter().enumerate() {
+                let action = if index == remaining.len() - 1 {
+                    ActionKind::Scissors
+                } else {
+                    ActionKind::Rock
+                };
+                server_data
+                    .play_round(player.id, game_data.id, action)
+                    .unwrap();
+            }
+
+            let game_data = server_data
+                .get_game_data(players[0].id, game_data.id)
+                .unwrap();
+            let eliminated = remaining.last().unwrap().id;
+            assert_eq!(
+                game_data.round_history[round].result,
+                Some(vec![RoundResult::Eliminated(eliminated)])
+            );
+            assert!(server_data
+                .play_round(eliminated, game_data.id, ActionKind::Rock)
+                .is_err());
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(
+            game_data.eliminated,
+            vec![players[3].id, players[2].id, players[1].id]
+        );
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, Some(players[0].id));
+    }
+
+    #[test]
+    fn test_stale_version_is_rejected() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+        let version = game_data.version;
+
+        server_data
+            .play_round_at_version(players[0].id, game_data.id, ActionKind::Rock, Some(version))
+            .unwrap();
+
+        //bob didn't see alice's move
+        assert_eq!(
+            server_data
+                .play_round_at_version(
+                    players[1].id,
+                    game_data.id,
+                    ActionKind::Paper,
+                    Some(version)
+                )
+                .unwrap_err(),
+            GameError::StaleVersion
+        );
+
+        let game_data = server_data
+            .play_round_at_version(
+                players[1].id,
+                game_data.id,
+                ActionKind::Paper,
+                Some(version + 1),
+            )
+            .unwrap();
+        assert_eq!(game_data.round_history.len(), 1);
+    }
+
+    #[test]
+    fn test_max_hosted_rooms() {
+        let mut server_data = ServerData::default();
+        server_data.config.max_hosted_rooms = 2;
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+
+        let first_room = server_data
+            .create_room(alice.id, "first room".to_string(), None)
+            .unwrap();
+        let second_room = server_data
+            .create_room(alice.id, "second room".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            server_data
+                .create_room(alice.id, "third room".to_string(), None)
+                .unwrap_err(),
+            GameError::TooManyRooms
+        );
+
+        //launching a room frees a slot
+        server_data
+            .join_room_with_password(bob.id, first_room.id, None)
+            .unwrap();
+        server_data
+            .set_ready(alice.id, first_room.id, true)
+            .unwrap();
+        server_data.set_ready(bob.id, first_room.id, true).unwrap();
+        server_data.launch_room(alice.id, first_room.id).unwrap();
+        server_data
+            .create_room(alice.id, "third room".to_string(), None)
+            .unwrap();
+        assert!(server_data
+            .create_room(alice.id, "fourth room".to_string(), None)
+            .is_err());
+
+        //so does leaving a room, which deletes it once empty
+        server_data.leave_room(alice.id, second_room.id).unwrap();
+        let fourth_room = server_data
+            .create_room(alice.id, "fourth room".to_string(), None)
+            .unwrap();
+
+        //a room left to other players doesn't count either, they host it now
+        server_data
+            .join_room_with_password(bob.id, fourth_room.id, None)
+            .unwrap();
+        server_data.leave_room(alice.id, fourth_room.id).unwrap();
+        server_data
+            .create_room(alice.id, "fifth room".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            server_data
+                .create_room(alice.id, "sixth room".to_string(), None)
+                .unwrap_err(),
+            GameError::TooManyRooms
+        );
+
+        //the limit is per player
+        server_data
+            .create_room(bob.id, "bob's room".to_string(), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_pairwise_results_are_ordered() {
+        let mut server_data = ServerData::default();
+        let (players, mut game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                ..GameSettings::default()
+            },
+        );
+
+        for (player, action) in
+            players
+                .iter()
+                .rev()
+                .zip([ActionKind::Scissors, ActionKind::Paper, ActionKind::Rock])
+        {
+            game_data.current_round.inputs.insert(player.id, action);
+        }
+
+        let round_results = ServerData::resolve_pairwise(&mut game_data.clone());
+        assert_eq!(
+            round_results,
+            vec![
+                RoundResult::Duel {
+                    players: [players[0].id, players[1].id],
+                    winner: Some(players[1].id)
+                },
+                RoundResult::Duel {
+                    players: [players[0].id, players[2].id],
+                    winner: Some(players[0].id)
+                },
+                RoundResult::Duel {
+                    players: [players[1].id, players[2].id],
+                    winner: Some(players[2].id)
+                },
+            ]
+        );
+        for _ in 0..10 {
+            assert_eq!(
+                ServerData::resolve_pairwise(&mut game_data.clone()),
+                round_results
+            );
+        }
+    }
+
+    #[test]
+    fn test_three_player_pairwise_round() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                ..GameSettings::default()
+            },
+        );
+        let (alice, bob, charlie) = (players[0].id, players[1].id, players[2].id);
+
+        let game_data = server_data
+            .play_batch(
+                game_data.id,
+                vec![
+                    (alice, ActionKind::Rock),
+                    (bob, ActionKind::Rock),
+                    (charlie, ActionKind::Scissors),
+                ],
+            )
+            .unwrap();
+
+        //Both rocks beat the scissors and tie with each other
+        let round = &game_data.round_history[0];
+        assert_eq!(
+            round.result,
+            Some(vec![
+                RoundResult::Duel {
+                    players: [alice, bob],
+                    winner: None
+                },
+                RoundResult::Duel {
+                    players: [alice, charlie],
+                    winner: Some(alice)
+                },
+                RoundResult::Duel {
+                    players: [bob, charlie],
+                    winner: Some(bob)
+                },
+            ])
+        );
+        assert_eq!(
+            game_data
+                .players
+                .iter()
+                .map(|(_, score)| *score)
+                .collect_vec(),
+            vec![1, 1, 0]
+        );
+        assert_eq!(
+            round.per_player,
+            BTreeMap::from([
+                (alice, PlayerRoundOutcome::Mixed),
+                (bob, PlayerRoundOutcome::Mixed),
+                (charlie, PlayerRoundOutcome::Lost),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_custom_win_table() {
+        let mut server_data = ServerData::default();
+
+        let dominant_rock = GameKind::Custom {
+            wins: vec![
+                (ActionKind::Rock, ActionKind::Scissors),
+                (ActionKind::Rock, ActionKind::Paper),
+                (ActionKind::Scissors, ActionKind::Paper),
+            ],
+        };
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                kind: dominant_rock,
+                end_condition: EndCondition::TotalRounds(3),
+                ..GameSettings::default()
+            },
+        );
+
+        for action in [ActionKind::Paper, ActionKind::Scissors, ActionKind::Paper] {
+            server_data
+                .play_round(players[0].id, game_data.id, ActionKind::Rock)
+                .unwrap();
+            server_data
+                .play_round(players[1].id, game_data.id, action)
+                .unwrap();
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert!(game_data.round_history.iter().all(|round| round.result
+            == Some(vec![RoundResult::Duel {
+                players: [players[0].id, players[1].id],
+                winner: Some(players[0].id)
+            }])));
+        assert_eq!(game_data.winner, Some(players[0].id));
+
+        //tables with an action beating itself or mutual wins are refused
+        for wins in [
+            vec![(ActionKind::Rock, ActionKind::Rock)],
+            vec![
+                (ActionKind::Rock, ActionKind::Paper),
+                (ActionKind::Paper, ActionKind::Rock),
+            ],
+        ] {
+            assert_eq!(
+                server_data
+                    .create_room(
+                        players[0].id,
+                        "broken room".to_string(),
+                        Some(GameSettings {
+                            kind: GameKind::Custom { wins },
+                            ..GameSettings::default()
+                        }),
+                    )
+                    .unwrap_err(),
+                GameError::InvalidWinTable
+            );
+        }
+    }
+
+    #[test]
+    fn test_player_count_limits() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+
+        let create_room = |server_data: &mut ServerData, kind: GameKind, player_count| {
+            server_data.create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    kind,
+                    player_count,
+                    ..GameSettings::default()
+                }),
+            )
+        };
+
+        for player_count in [0, 1, 9, 50] {
+            assert_eq!(
+                create_room(&mut server_data, GameKind::RockPaperScissors, player_count)
+                    .unwrap_err(),
+                GameError::InvalidPlayerCount
+            );
+        }
+        assert_eq!(
+            create_room(&mut server_data, GameKind::Chess, 3).unwrap_err(),
+            GameError::InvalidPlayerCount
+        );
+
+        assert!(create_room(&mut server_data, GameKind::RockPaperScissors, 2).is_ok());
+        assert!(create_room(&mut server_data, GameKind::RockPaperScissors, 8).is_ok());
+        assert!(create_room(&mut server_data, GameKind::Chess, 2).is_ok());
+    }
+
+    #[test]
+    fn test_friends_only_rooms() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+
+        assert_eq!(
+            server_data.add_friend(alice.id, alice.id).unwrap_err(),
+            GameError::SelfFriend
+        );
+        server_data.add_friend(alice.id, bob.id).unwrap();
+
+        let room_data = server_data
+            .create_room(alice.id, "friends room".to_string(), None)
+            .unwrap();
+        assert!(server_data
+            .set_room_visibility(bob.id, room_data.id, RoomVisibility::FriendsOnly)
+            .is_err());
+        server_data
+            .set_room_visibility(alice.id, room_data.id, RoomVisibility::FriendsOnly)
+            .unwrap();
+
+        let visible_to = |server_data: &mut ServerData, player_id| {
+            !server_data.get_rooms_filtered(player_id).is_empty()
+        };
+        assert!(visible_to(&mut server_data, Some(alice.id)));
+        assert!(visible_to(&mut server_data, Some(bob.id)));
+        assert!(!visible_to(&mut server_data, Some(charlie.id)));
+        assert!(!visible_to(&mut server_data, None));
+
+        server_data.remove_friend(alice.id, bob.id).unwrap();
+        assert!(!visible_to(&mut server_data, Some(bob.id)));
+    }
+
+    #[test]
+    fn test_identical_action_is_a_no_op() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                allow_action_change: false,
+                action_cooldown_ms: Some(1000),
+                round_timeout_secs: None,
+                spectator_delay_secs: None,
+                ..GameSettings::default()
+            },
+        );
+
+        let played = server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        server_data.clock.advance(10);
+        let replayed = server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        assert_eq!(replayed.version, played.version);
+        assert_eq!(
+            replayed.current_round.submitted_at,
+            played.current_round.submitted_at
+        );
+
+        //a different action is still refused once locked in
+        assert!(server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Paper)
+            .is_err());
+    }
+
+    #[test]
+    fn test_ephemeral_players() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_ephemeral_player("Alice".to_string())
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        assert!(!server_data.players.contains_key(&alice.id));
+
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "quick game".to_string(),
+                Some(GameSettings {
+                    end_condition: EndCondition::TotalRounds(1),
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        server_data.set_ready(alice.id, room_data.id, true).unwrap();
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        let game_data = server_data.launch_room(alice.id, room_data.id).unwrap();
+
+        server_data
+            .play_round(alice.id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        let game_data = server_data
+            .play_round(bob.id, game_data.id, ActionKind::Scissors)
+            .unwrap();
+        assert_eq!(game_data.winner, Some(alice.id));
+
+        //alice is gone with her game, and never was on the leaderboard
+        assert!(server_data.ephemeral_players.is_empty());
+        assert!(server_data.get_game_data(alice.id, game_data.id).is_err());
+        assert_eq!(
+            server_data
+                .leaderboard(10)
+                .into_iter()
+                .map(|(player, stats)| (player.id, stats.wins))
+                .collect_vec(),
+            vec![(bob.id, 0)]
+        );
+
+        server_data.config.allow_ephemeral_players = false;
+        assert_eq!(
+            server_data
+                .create_ephemeral_player("Charlie".to_string())
+                .unwrap_err(),
+            GameError::EphemeralPlayersDisabled
+        );
+    }
+
+    #[test]
+    fn test_launch_with_bots() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    end_condition: EndCondition::TotalRounds(3),
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+
+        let game_data = server_data
+            .launch_room_with_bots(alice.id, room_data.id, BotStrategy::AlwaysRock)
+            .unwrap();
+        assert_eq!(game_data.players.len(), 2);
+        let bot_id = game_data.players[1].0.id;
+
+        for _ in 0..3 {
+            server_data
+                .play_round(alice.id, game_data.id, ActionKind::Paper)
+                .unwrap();
+        }
+
+        let game_data = ServerData::lock_game(&server_data.games[&game_data.id]).clone();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, Some(alice.id));
+        assert!(game_data
+            .round_history
+            .iter()
+            .all(|round| round.inputs.get(&bot_id) == Some(&ActionKind::Rock)));
+
+        //the bot went away with its game
+        assert!(server_data.bots.is_empty());
+        assert!(server_data.ephemeral_players.is_empty());
+    }
+
+    #[test]
+    fn test_counter_last_bot() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
+
+        let game_data = server_data
+            .launch_room_with_bots(alice.id, room_data.id, BotStrategy::CounterLast)
+            .unwrap();
+        let bot_id = game_data.players[1].0.id;
+
+        for action in [ActionKind::Rock, ActionKind::Scissors, ActionKind::Paper] {
+            server_data
+                .play_round(alice.id, game_data.id, action)
+                .unwrap();
+        }
+
+        let game_data = ServerData::lock_game(&server_data.games[&game_data.id]).clone();
+        let bot_actions = game_data
+            .round_history
+            .iter()
+            .skip(1)
+            .map(|round| round.inputs[&bot_id].clone())
+            .collect_vec();
+        assert_eq!(bot_actions, vec![ActionKind::Paper, ActionKind::Rock]);
+        assert_eq!(
+            game_data.current_round.inputs.get(&bot_id),
+            Some(&ActionKind::Scissors)
+        );
+    }
+
+    #[test]
+    fn test_room_with_bots() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    player_count: 3,
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(
+            server_data.seat_bots(room_data.id, 3, BotStrategy::Random),
+            Err(GameError::NotEnoughSeats)
+        );
+        //bots never play what the game doesn't allow
+        assert_eq!(
+            server_data.seat_bots(
+                room_data.id,
+                1,
+                BotStrategy::Replay(vec![ActionKind::Lizard])
+            ),
+            Err(GameError::ActionNotAvailable)
+        );
+        let room_data = server_data
+            .seat_bots(room_data.id, 1, BotStrategy::Random)
+            .unwrap();
+        let bot_id = room_data.players[1].id;
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+
+        //Bob takes over as host rather than the bot seated before him
+        server_data.leave_room(alice.id, room_data.id).unwrap();
+        let room_data = server_data.get_room_data(bob.id, room_data.id).unwrap();
+        assert_eq!(
+            room_data
+                .players
+                .iter()
+                .map(|player| player.id)
+                .collect_vec(),
+            vec![bob.id, bot_id]
+        );
+
+        //The bot goes away with the room once no human is left
+        server_data.leave_room(bob.id, room_data.id).unwrap();
+        assert!(server_data.rooms.is_empty());
+        assert!(server_data.bots.is_empty());
+        assert!(server_data.ephemeral_players.is_empty());
+    }
+
+    #[test]
+    fn test_per_player_outcomes() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                end_condition: EndCondition::TotalRounds(3),
+                ..GameSettings::default()
+            },
+        );
+
+        let rounds = [
+            [ActionKind::Rock, ActionKind::Scissors, ActionKind::Scissors],
+            [ActionKind::Paper, ActionKind::Rock, ActionKind::Paper],
+            [ActionKind::Rock, ActionKind::Rock, ActionKind::Rock],
+        ];
+        for actions in rounds {
+            for (player, action) in players.iter().zip(actions) {
+                server_data
+                    .play_round(player.id, game_data.id, action)
+                    .unwrap();
+            }
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        let outcomes = |round: usize| {
+            players
+                .iter()
+                .map(|player| game_data.round_history[round].per_player[&player.id].clone())
+                .collect_vec()
+        };
+
+        //alice beats both scissors, which draw against each other
+        assert_eq!(
+            outcomes(0),
+            vec![
+                PlayerRoundOutcome::Won,
+                PlayerRoundOutcome::Mixed,
+                PlayerRoundOutcome::Mixed
+            ]
+        );
+        //bob's rock loses to both papers
+        assert_eq!(
+            outcomes(1),
+            vec![
+                PlayerRoundOutcome::Mixed,
+                PlayerRoundOutcome::Lost,
+                PlayerRoundOutcome::Mixed
+            ]
+        );
+        assert_eq!(outcomes(2), vec![PlayerRoundOutcome::Drew; 3]);
+    }
+
+    #[test]
+    fn test_replay_practice() {
+        let mut server_data = ServerData::default();
+        let (players, source_game) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                end_condition: EndCondition::TotalRounds(3),
+                ..GameSettings::default()
+            },
+        );
+
+        //alice is recorded playing rock, paper then scissors
+        for action in [ActionKind::Rock, ActionKind::Paper, ActionKind::Scissors] {
+            server_data
+                .play_round(players[0].id, source_game.id, action)
+                .unwrap();
+            server_data
+                .play_round(players[1].id, source_game.id, ActionKind::Rock)
+                .unwrap();
+        }
+
+        let practice_game = server_data
+            .start_replay_practice(players[1].id, source_game.id)
+            .unwrap();
+        let ghost_id = practice_game.players[1].0.id;
+
+        for action in [ActionKind::Paper, ActionKind::Paper, ActionKind::Rock] {
+            server_data
+                .play_round(players[1].id, practice_game.id, action)
+                .unwrap();
+        }
+
+        let practice_game = ServerData::lock_game(&server_data.games[&practice_game.id]).clone();
+        assert_eq!(
+            practice_game
+                .round_history
+                .iter()
+                .map(|round| round.inputs[&ghost_id].clone())
+                .collect_vec(),
+            vec![ActionKind::Rock, ActionKind::Paper, ActionKind::Scissors]
+        );
+        assert_eq!(
+            practice_game
+                .players
+                .iter()
+                .map(|(_, score)| *score)
+                .collect_vec(),
+            vec![2, 0]
+        );
+        assert_eq!(practice_game.status, GameStatus::Ended);
+        assert_eq!(practice_game.winner, Some(players[1].id));
+    }
+
+    #[test]
+    fn test_score_deltas() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                ..GameSettings::default()
+            },
+        );
+
+        for (player, action) in
+            players
+                .iter()
+                .zip([ActionKind::Rock, ActionKind::Scissors, ActionKind::Scissors])
+        {
+            server_data
+                .play_round(player.id, game_data.id, action)
+                .unwrap();
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        let round = &game_data.round_history[0];
+
+        let points_awarded = round
+            .result
+            .iter()
+            .flatten()
+            .filter(|result| {
+                matches!(
+                    result,
+                    RoundResult::Duel {
+                        winner: Some(_),
+                        ..
+                    }
+                )
+            })
+            .count() as i64;
+        assert_eq!(round.score_deltas.values().sum::<i64>(), points_awarded);
+        assert_eq!(
+            round.score_deltas,
+            BTreeMap::from([(players[0].id, 2), (players[1].id, 0), (players[2].id, 0)])
+        );
+    }
+
+    #[test]
+    fn test_active_player_count() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        server_data.config.idle_after_ms = 1000;
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        assert_eq!(server_data.active_player_count(), 3);
+
+        server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
+        server_data.clock.advance(1000);
+        server_data.heartbeat(bob.id).unwrap();
+
+        //alice waits in her room, bob keeps sending heartbeats, charlie is idle
+        assert_eq!(server_data.players.len(), 3);
+        assert_eq!(server_data.active_player_count(), 2);
+    }
+
+    #[test]
+    fn test_reveal_order() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                ..GameSettings::default()
+            },
+        );
+
+        for player in [&players[2], &players[0], &players[1]] {
+            server_data.clock.advance(100);
+            server_data
+                .play_round(player.id, game_data.id, ActionKind::Rock)
+                .unwrap();
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(
+            game_data.round_history[0].reveal_order,
+            vec![players[2].id, players[0].id, players[1].id]
+        );
+    }
+
+    fn launch_chess_game(server_data: &mut ServerData) -> (Vec<PlayerData>, GameData) {
+        launch_game(
+            server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                kind: GameKind::Chess,
+                end_condition: EndCondition::TotalRounds(10),
+                ..GameSettings::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_chess_pawn_push() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_chess_game(&mut server_data);
+
+        let pawn_push = ActionKind::Move {
+            from: chess::Square::new(4, 1),
+            to: chess::Square::new(4, 3),
+        };
+        let game_data = server_data
+            .play_round(players[0].id, game_data.id, pawn_push)
+            .unwrap();
+
+        let board = game_data.board.unwrap();
+        assert_eq!(board.piece_at(chess::Square::new(4, 1)), None);
+        assert_eq!(
+            board.piece_at(chess::Square::new(4, 3)),
+            Some(chess::ChessPiece {
+                kind: chess::PieceKind::Pawn,
+                color: ChessColor::White,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chess_rook_blocked() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_chess_game(&mut server_data);
+
+        //black can't move before white
+        let black_pawn_push = ActionKind::Move {
+            from: chess::Square::new(0, 6),
+            to: chess::Square::new(0, 5),
+        };
+        assert_eq!(
+            server_data
+                .play_round(players[1].id, game_data.id, black_pawn_push)
+                .unwrap_err(),
+            GameError::NotYourTurn
+        );
+
+        //the rook would have to go through its own pawn
+        let rook_through_pawn = ActionKind::Move {
+            from: chess::Square::new(0, 0),
+            to: chess::Square::new(0, 4),
+        };
+        assert_eq!(
+            server_data
+                .play_round(players[0].id, game_data.id, rook_through_pawn)
+                .unwrap_err(),
+            GameError::IllegalMove
+        );
+        assert_eq!(
+            server_data
+                .play_round(players[0].id, game_data.id, ActionKind::Rock)
+                .unwrap_err(),
+            GameError::ActionNotAvailable
+        );
+    }
+
+    #[test]
+    fn test_reset_current_round() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+
+        server_data
+            .play_round(players[1].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        assert_eq!(
+            server_data
+                .reset_current_round(players[1].id, game_data.id)
+                .unwrap_err(),
+            GameError::NotHost
+        );
+        let reset_game = server_data
+            .reset_current_round(players[0].id, game_data.id)
+            .unwrap();
+        assert!(reset_game.current_round.inputs.is_empty());
+        assert!(reset_game.round_history.is_empty());
+
+        //bob can pick another action for the replayed round
+        server_data
+            .play_round(players[1].id, game_data.id, ActionKind::Paper)
+            .unwrap();
+        let game_data = server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        assert_eq!(game_data.round_history.len(), 1);
+        assert_eq!(
+            game_data.round_history[0].result,
+            Some(vec![RoundResult::Duel {
+                players: [players[0].id, players[1].id],
+                winner: Some(players[1].id)
+            }])
+        );
+    }
+
+    #[test]
+    fn test_rock_paper_scissors_lizard_spock() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                kind: GameKind::RockPaperScissorsLizardSpock,
+                end_condition: EndCondition::TotalRounds(5),
+                ..GameSettings::default()
+            },
+        );
+
+        //alice wins with every gesture but the last one, a draw
+        let rounds = [
+            (ActionKind::Rock, ActionKind::Lizard),
+            (ActionKind::Paper, ActionKind::Spock),
+            (ActionKind::Scissors, ActionKind::Lizard),
+            (ActionKind::Lizard, ActionKind::Spock),
+            (ActionKind::Spock, ActionKind::Spock),
+        ];
+        for (alice_action, bob_action) in rounds {
+            server_data
+                .play_round(players[0].id, game_data.id, alice_action)
+                .unwrap();
+            server_data
+                .play_round(players[1].id, game_data.id, bob_action)
+                .unwrap();
+        }
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.players[0].1, 4);
+        assert_eq!(game_data.players[1].1, 0);
+        assert_eq!(game_data.winner, Some(players[0].id));
+    }
+
+    #[test]
+    fn test_lizard_spock_rejected_in_rock_paper_scissors() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+
+        for action in [ActionKind::Lizard, ActionKind::Spock] {
+            assert_eq!(
+                server_data
+                    .play_round(players[0].id, game_data.id, action)
+                    .unwrap_err(),
+                GameError::ActionNotAvailable
+            );
+        }
+    }
+
+    #[test]
+    fn test_deterministic_serialization() {
+        //The same game played twice, with the players acting in reverse order
+        let play = |reverse: bool| {
+            let mut server_data = ServerData {
+                clock: Clock::Manual(0),
+                ..Default::default()
+            };
+            let (players, game_data) = launch_game(
+                &mut server_data,
+                &["Alice", "Bob", "Charlie"],
+                GameSettings {
+                    player_count: 3,
+                    scoring_mode: ScoringMode::Survival,
+                    tie_break: TieBreak::Draw,
+                    ..GameSettings::default()
+                },
+            );
+
+            let mut moves = players
+                .iter()
+                .zip([ActionKind::Rock, ActionKind::Scissors, ActionKind::Scissors])
+                .collect_vec();
+            if reverse {
+                moves.reverse();
+            }
+            for (player, action) in moves {
+                server_data
+                    .play_round(player.id, game_data.id, action)
+                    .unwrap();
+            }
+            let dave = server_data
+                .create_player_with_region("Dave".to_string(), None)
+                .unwrap();
+            server_data.spectate_game(dave.id, game_data.id).unwrap();
+
+            let game_data = server_data
+                .get_game_data(players[0].id, game_data.id)
+                .unwrap();
+            (
+                serde_json::to_string(&net::LaunchGetGameResponse::from(game_data.clone()))
+                    .unwrap(),
+                serde_json::to_string(&game_data.to_spectator_response(0)).unwrap(),
+            )
+        };
+
+        assert_eq!(play(false), play(false));
+        assert_eq!(play(false), play(true));
+    }
+
+    #[test]
+    fn test_round_timeout() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                round_timeout_secs: Some(30),
+                ..GameSettings::default()
+            },
+        );
+
+        server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        server_data.clock.advance(29_000);
+        assert!(server_data.expire_rounds().is_empty());
+
+        server_data.clock.advance(1000);
+        assert_eq!(server_data.expire_rounds(), vec![game_data.id]);
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        let round = &game_data.round_history[0];
+        assert_eq!(round.timed_out, vec![players[1].id]);
+        assert_eq!(round.per_player[&players[0].id], PlayerRoundOutcome::Won);
+        assert_eq!(round.per_player[&players[1].id], PlayerRoundOutcome::Lost);
+        assert_eq!(game_data.players[0].1, 1);
+        assert_eq!(game_data.players[1].1, 0);
+
+        //the next round gets a fresh timeout, and is left alone while nobody
+        //has played
+        assert_eq!(game_data.round_started_at, 30_000);
+        server_data.clock.advance(60_000);
+        assert!(server_data.expire_rounds().is_empty());
+    }
+
+    #[test]
+    fn test_round_timestamps() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+        assert_eq!(game_data.current_round.started_at, 0);
+        assert_eq!(game_data.current_round.resolved_at, None);
+
+        server_data.clock.advance(5000);
+        server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        server_data.clock.advance(7000);
+        server_data
+            .play_round(players[1].id, game_data.id, ActionKind::Paper)
+            .unwrap();
+
+        //the next round starts as soon as the previous one is resolved
+        server_data.clock.advance(3000);
+        server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        server_data
+            .play_round(players[1].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        let rounds = &game_data.round_history;
+        assert_eq!(
+            (rounds[0].started_at, rounds[0].resolved_at),
+            (0, Some(12_000))
+        );
+        assert_eq!(
+            (rounds[1].started_at, rounds[1].resolved_at),
+            (12_000, Some(15_000))
+        );
+        assert_eq!(game_data.current_round.started_at, 15_000);
+        assert_eq!(game_data.current_round.resolved_at, None);
+    }
+
+    #[test]
+    fn test_pause_game() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                round_timeout_secs: Some(30),
+                ..GameSettings::default()
+            },
+        );
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            server_data.pause_game(charlie.id, game_data.id).err(),
+            Some(GameError::NotInGame)
+        );
+
+        server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        server_data.clock.advance(10_000);
+        let game_data = server_data.pause_game(players[1].id, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Paused);
+        assert_eq!(
+            server_data
+                .play_round(players[1].id, game_data.id, ActionKind::Paper)
+                .err(),
+            Some(GameError::GamePaused)
+        );
+        //even sending the action already played again
+        assert_eq!(
+            server_data
+                .play_round(players[0].id, game_data.id, ActionKind::Rock)
+                .err(),
+            Some(GameError::GamePaused)
+        );
+        assert!(server_data
+            .get_legal_actions(players[1].id, game_data.id)
+            .unwrap()
+            .is_empty());
+
+        //the round timeout doesn't run while the game is paused
+        server_data.clock.advance(60_000);
+        assert!(server_data.expire_rounds().is_empty());
+
+        let game_data = server_data
+            .resume_game(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Running);
+        assert_eq!(
+            game_data.current_round.inputs.get(&players[0].id),
+            Some(&ActionKind::Rock)
+        );
+        //resuming twice is harmless
+        let game_data = server_data
+            .resume_game(players[0].id, game_data.id)
+            .unwrap();
+
+        server_data.clock.advance(19_000);
+        assert!(server_data.expire_rounds().is_empty());
+        server_data.clock.advance(1000);
+        assert_eq!(server_data.expire_rounds(), vec![game_data.id]);
+    }
+
+    #[test]
+    fn test_spectator_delay() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                spectator_delay_secs: Some(10),
+                ..GameSettings::default()
+            },
+        );
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+
+        server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        let delayed_actions = |server_data: &mut ServerData| {
+            let game_data = server_data.spectate_game(charlie.id, game_data.id).unwrap();
+            let response =
+                serde_json::to_value(game_data.to_spectator_response(server_data.clock.now()))
+                    .unwrap();
+            response["delayed_actions"].clone()
+        };
+
+        server_data.clock.advance(9_999);
+        assert_eq!(delayed_actions(&mut server_data), serde_json::json!({}));
+
+        server_data.clock.advance(1);
+        assert_eq!(
+            delayed_actions(&mut server_data),
+            serde_json::json!({ players[0].id.to_string(): "Rock" })
+        );
+    }
+
+    #[test]
+    fn test_player_analytics() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+
+        //alice loves rock, and wins the first game only
+        for rounds in [
+            [
+                (ActionKind::Rock, ActionKind::Scissors),
+                (ActionKind::Rock, ActionKind::Scissors),
+                (ActionKind::Paper, ActionKind::Scissors),
+            ],
+            [
+                (ActionKind::Rock, ActionKind::Paper),
+                (ActionKind::Rock, ActionKind::Paper),
+                (ActionKind::Scissors, ActionKind::Rock),
+            ],
+        ] {
+            let room_data = server_data
+                .create_room(
+                    alice.id,
+                    "test room".to_string(),
+                    Some(GameSettings {
+                        end_condition: EndCondition::TotalRounds(3),
+                        ..GameSettings::default()
+                    }),
+                )
+                .unwrap();
+            server_data
+                .join_room_with_password(bob.id, room_data.id, None)
+                .unwrap();
+            server_data.set_ready(alice.id, room_data.id, true).unwrap();
+            server_data.set_ready(bob.id, room_data.id, true).unwrap();
+            let game_data = server_data.launch_room(alice.id, room_data.id).unwrap();
+
+            for (alice_action, bob_action) in rounds {
+                server_data
+                    .play_round(alice.id, game_data.id, alice_action)
+                    .unwrap();
+                server_data
+                    .play_round(bob.id, game_data.id, bob_action)
+                    .unwrap();
+            }
+        }
+
+        let analytics = server_data.player_analytics(alice.id).unwrap();
+        assert_eq!(analytics.games_played, 2);
+        assert_eq!(analytics.games_won, 1);
+        assert_eq!(analytics.win_rate, 0.5);
+        assert_eq!(
+            analytics.action_counts,
+            vec![
+                (ActionKind::Rock, 4),
+                (ActionKind::Paper, 1),
+                (ActionKind::Scissors, 1)
+            ]
+        );
+        assert_eq!(analytics.favorite_action, Some(ActionKind::Rock));
+    }
+
+    #[test]
+    fn test_host_promotion_notification() {
+        let mut server_data = ServerData::default();
+
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    player_count: 3,
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .unwrap();
+
+        //a guest leaving doesn't change the host
+        server_data.leave_room(charlie.id, room_data.id).unwrap();
+        assert!(server_data.take_notifications(bob.id).unwrap().is_empty());
+
+        //bob takes over when alice leaves, and hears about it once
+        server_data.leave_room(alice.id, room_data.id).unwrap();
+        assert_eq!(
+            server_data.take_notifications(bob.id).unwrap(),
+            vec![Notification::PromotedToHost {
+                room_id: room_data.id
+            }]
+        );
+        assert!(server_data.take_notifications(bob.id).unwrap().is_empty());
+        assert!(server_data.take_notifications(alice.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_games_batch() {
+        let mut server_data = ServerData::default();
+        let settings = GameSettings::default();
+
+        let (players, first_game) =
+            launch_game(&mut server_data, &["Alice", "Bob"], settings.clone());
+        let (_, other_game) = launch_game(&mut server_data, &["Charlie", "Dave"], settings);
+
+        let alice = &players[0];
+        let eve = server_data
+            .create_player_with_region("Eve".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(alice.id, "second room".to_string(), None)
+            .unwrap();
+        server_data
+            .join_room_with_password(eve.id, room_data.id, None)
+            .unwrap();
+        server_data.set_ready(alice.id, room_data.id, true).unwrap();
+        server_data.set_ready(eve.id, room_data.id, true).unwrap();
+        let second_game = server_data.launch_room(alice.id, room_data.id).unwrap();
+
+        //other players' games and unknown ones are skipped, the order is kept
+        let games = server_data
+            .get_games_batch(
+                alice.id,
+                &[
+                    second_game.id,
+                    99,
+                    other_game.id,
+                    first_game.id,
+                    first_game.id,
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            games.iter().map(|game| game.id).collect_vec(),
+            vec![second_game.id, first_game.id]
+        );
+
+        assert_eq!(
+            server_data
+                .get_games_batch(99, &[first_game.id])
+                .unwrap_err(),
+            GameError::UnknownPlayer
+        );
+    }
+
+    #[test]
+    fn test_ended_game_grace_period() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        server_data.config.ended_game_grace_ms = 1000;
+
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                end_condition: EndCondition::FirstToScore(1),
+                ..GameSettings::default()
+            },
+        );
+        server_data
+            .play_round(players[0].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        let game_data = server_data
+            .play_round(players[1].id, game_data.id, ActionKind::Scissors)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+
+        //the final result can still be fetched during the grace period
+        server_data.clock.advance(999);
+        assert!(server_data.archive_ended_games().is_empty());
+        assert!(server_data
+            .get_game_data(players[1].id, game_data.id)
+            .is_ok());
+
+        //then the game is archived, but it still counts for the leaderboard
+        server_data.clock.advance(1);
+        assert_eq!(server_data.archive_ended_games(), vec![game_data.id]);
+        assert_eq!(
+            server_data
+                .get_game_data(players[1].id, game_data.id)
+                .unwrap_err(),
+            GameError::UnknownGame
+        );
+        let (leader, stats) = &server_data.leaderboard(10)[0];
+        assert_eq!((leader.id, stats.wins), (players[0].id, 1));
+    }
+
+    #[test]
+    fn test_sudden_death_tie_break() {
+        let mut server_data = ServerData::default();
+        let settings = GameSettings {
+            player_count: 3,
+            end_condition: EndCondition::FirstToScore(1),
+            tie_break: TieBreak::SuddenDeath(1),
+            ..GameSettings::default()
+        };
+
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Carol"],
+            settings.clone(),
+        );
+        let (alice, bob, carol) = (players[0].id, players[1].id, players[2].id);
+
+        //Alice and Bob both reach the target score
+        server_data
+            .play_batch(
+                game_data.id,
+                vec![
+                    (alice, ActionKind::Rock),
+                    (bob, ActionKind::Rock),
+                    (carol, ActionKind::Scissors),
+                ],
+            )
+            .unwrap();
+        let game_data = server_data.get_game_data(alice, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Running);
+        assert_eq!(game_data.tie_break_rounds, 1);
+
+        server_data
+            .play_batch(
+                game_data.id,
+                vec![
+                    (alice, ActionKind::Paper),
+                    (bob, ActionKind::Rock),
+                    (carol, ActionKind::Rock),
+                ],
+            )
+            .unwrap();
+        let game_data = server_data.get_game_data(alice, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, Some(alice));
+        assert_eq!(game_data.winners, vec![alice]);
+        assert_eq!(game_data.round_history.len(), 2);
+
+        //The game gives up on the tie once the replay rounds are spent
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Dave", "Erin", "Frank"], settings);
+        for _ in 0..2 {
+            server_data
+                .play_batch(
+                    game_data.id,
+                    vec![
+                        (players[0].id, ActionKind::Rock),
+                        (players[1].id, ActionKind::Rock),
+                        (players[2].id, ActionKind::Scissors),
+                    ],
+                )
+                .unwrap();
+        }
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, None);
+        assert_eq!(game_data.winners, vec![players[0].id, players[1].id]);
+    }
+
+    #[test]
+    fn test_shared_win() {
+        let mut server_data = ServerData::default();
+        let settings = GameSettings {
+            player_count: 3,
+            end_condition: EndCondition::FirstToScore(1),
+            ..GameSettings::default()
+        };
+
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob", "Carol"], settings);
+        let (alice, bob, carol) = (players[0].id, players[1].id, players[2].id);
+
+        //Alice and Bob reach the target score together
+        server_data
+            .play_batch(
+                game_data.id,
+                vec![
+                    (alice, ActionKind::Rock),
+                    (bob, ActionKind::Rock),
+                    (carol, ActionKind::Scissors),
+                ],
+            )
+            .unwrap();
+        let game_data = server_data.get_game_data(carol, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, None);
+        assert_eq!(game_data.winners, vec![alice, bob]);
+    }
+
+    #[test]
+    fn test_player_stats() {
+        let mut server_data = ServerData::default();
+        let settings = GameSettings {
+            player_count: 3,
+            end_condition: EndCondition::FirstToScore(1),
+            ..GameSettings::default()
+        };
+
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob", "Carol"], settings);
+        let (alice, bob, carol) = (players[0].id, players[1].id, players[2].id);
+        assert_eq!(
+            server_data.player_stats(alice).unwrap(),
+            PlayerStats::default()
+        );
+
+        //Alice and Bob share the win, Carol loses
+        server_data
+            .play_batch(
+                game_data.id,
+                vec![
+                    (alice, ActionKind::Rock),
+                    (bob, ActionKind::Rock),
+                    (carol, ActionKind::Scissors),
+                ],
+            )
+            .unwrap();
+
+        //Then Alice beats Bob, and a game where every round is a draw is
+        //stopped by its round cap
+        for (alice_action, rounds) in [(ActionKind::Paper, 1), (ActionKind::Rock, 2)] {
+            let room_data = server_data
+                .create_room(
+                    alice,
+                    "test room".to_string(),
+                    Some(GameSettings {
+                        end_condition: EndCondition::FirstToScore(1),
+                        max_rounds: 2,
+                        ..GameSettings::default()
+                    }),
+                )
+                .unwrap();
+            server_data
+                .join_room_with_password(bob, room_data.id, None)
+                .unwrap();
+            server_data.set_ready(alice, room_data.id, true).unwrap();
+            server_data.set_ready(bob, room_data.id, true).unwrap();
+            let game_data = server_data.launch_room(alice, room_data.id).unwrap();
+            for _ in 0..rounds {
+                server_data
+                    .play_batch(
+                        game_data.id,
+                        vec![(alice, alice_action.clone()), (bob, ActionKind::Rock)],
+                    )
+                    .unwrap();
+            }
+        }
+
+        assert_eq!(
+            server_data.player_stats(alice).unwrap(),
+            PlayerStats {
+                wins: 1,
+                losses: 0,
+                draws: 2,
+                games_played: 3,
+            }
+        );
+        assert_eq!(
+            server_data.player_stats(bob).unwrap(),
+            PlayerStats {
+                wins: 0,
+                losses: 1,
+                draws: 2,
+                games_played: 3,
+            }
+        );
+        assert_eq!(
+            server_data.player_stats(carol).unwrap(),
+            PlayerStats {
+                wins: 0,
+                losses: 1,
+                draws: 0,
+                games_played: 1,
+            }
+        );
+        assert_eq!(
+            server_data.player_stats(1234).unwrap_err(),
+            GameError::UnknownPlayer
+        );
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut server_data = ServerData::default();
+        let settings = GameSettings {
+            end_condition: EndCondition::FirstToScore(1),
+            ..GameSettings::default()
+        };
+        let [alice, bob, carol, dave] = ["Alice", "Bob", "Carol", "Dave"].map(|name| {
+            server_data
+                .create_player_with_region(name.to_string(), None)
+                .unwrap()
+                .id
+        });
+
+        for (winner, loser) in [(alice, bob), (alice, carol), (carol, bob), (dave, bob)] {
+            let room_data = server_data
+                .create_room(winner, "test room".to_string(), Some(settings.clone()))
+                .unwrap();
+            server_data
+                .join_room_with_password(loser, room_data.id, None)
+                .unwrap();
+            server_data.set_ready(winner, room_data.id, true).unwrap();
+            server_data.set_ready(loser, room_data.id, true).unwrap();
+            let game_data = server_data.launch_room(winner, room_data.id).unwrap();
+            server_data
+                .play_batch(
+                    game_data.id,
+                    vec![(winner, ActionKind::Rock), (loser, ActionKind::Scissors)],
+                )
+                .unwrap();
+        }
+
+        //A win against a bot counts for Alice, the bot is never ranked
+        let room_data = server_data
+            .create_room(alice, "test room".to_string(), Some(settings))
+            .unwrap();
+        let game_data = server_data
+            .launch_room_with_bots(alice, room_data.id, BotStrategy::AlwaysRock)
+            .unwrap();
+        server_data
+            .play_round(alice, game_data.id, ActionKind::Paper)
+            .unwrap();
+
+        let ranking = |server_data: &ServerData, limit| {
+            server_data
+                .leaderboard(limit)
+                .into_iter()
+                .map(|(player, stats)| (player.id, stats.wins, stats.games_played))
+                .collect_vec()
+        };
+        //Dave and Carol both won once, Dave more often
+        assert_eq!(
+            ranking(&server_data, 10),
+            vec![(alice, 3, 3), (dave, 1, 1), (carol, 1, 2), (bob, 0, 3)]
+        );
+        assert_eq!(ranking(&server_data, 2), vec![(alice, 3, 3), (dave, 1, 1)]);
+    }
+
+    #[test]
+    fn test_forfeit_game() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], GameSettings::default());
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            server_data.forfeit_game(charlie.id, game_data.id).err(),
+            Some(GameError::NotInGame)
+        );
+
+        //alice led, bob gives up and alice wins anyway
+        server_data
+            .play_round(players[1].id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        let game_data = server_data
+            .forfeit_game(players[1].id, game_data.id)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, Some(players[0].id));
+        assert_eq!(game_data.winners, vec![players[0].id]);
+        assert_eq!(server_data.player_stats(players[0].id).unwrap().wins, 1);
+        assert_eq!(server_data.player_stats(players[1].id).unwrap().losses, 1);
+        assert_eq!(
+            server_data.forfeit_game(players[0].id, game_data.id).err(),
+            Some(GameError::GameNotRunning)
+        );
+
+        //with more players the game goes on without whoever forfeited
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Dave", "Erin", "Frank"],
+            GameSettings {
+                player_count: 3,
+                end_condition: EndCondition::TotalRounds(3),
+                ..GameSettings::default()
+            },
+        );
+        let [dave, erin, frank] = [&players[0], &players[1], &players[2]];
+        server_data
+            .play_round(dave.id, game_data.id, ActionKind::Scissors)
+            .unwrap();
+        server_data
+            .play_round(erin.id, game_data.id, ActionKind::Scissors)
+            .unwrap();
+        server_data
+            .play_round(frank.id, game_data.id, ActionKind::Rock)
+            .unwrap();
+
+        //the round was only waiting for frank
+        server_data
+            .play_round(dave.id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        server_data
+            .play_round(erin.id, game_data.id, ActionKind::Scissors)
+            .unwrap();
+        let game_data = server_data.forfeit_game(frank.id, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Running);
+        assert_eq!(game_data.round_history.len(), 2);
+        assert_eq!(game_data.required_submissions_remaining(), 2);
+        assert_eq!(
+            server_data
+                .play_round(frank.id, game_data.id, ActionKind::Rock)
+                .err(),
+            Some(GameError::PlayerForfeited)
+        );
+
+        server_data
+            .play_round(dave.id, game_data.id, ActionKind::Rock)
+            .unwrap();
+        let game_data = server_data
+            .play_round(erin.id, game_data.id, ActionKind::Scissors)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert!(game_data.players[2].1 > 0);
+        assert_eq!(game_data.winners, vec![dave.id]);
+    }
+
+    #[test]
+    fn test_disconnected_players_forfeit() {
+        let mut server_data = ServerData {
+            clock: Clock::Manual(0),
+            ..Default::default()
+        };
+        server_data.config.disconnect_after_ms = 1000;
 
-        Ok(())
-    }
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob", "Charlie"],
+            GameSettings {
+                player_count: 3,
+                ..GameSettings::default()
+            },
+        );
 
-    pub fn get_room_data(&self, player_id: PlayerId, room_id: RoomId) -> Result<RoomData> {
-        //Player must exist in players list
-        self.players
-            .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
+        server_data.clock.advance(600);
+        server_data.heartbeat(players[0].id).unwrap();
+        server_data.heartbeat(players[1].id).unwrap();
+        assert!(server_data.forfeit_disconnected_players().is_empty());
 
-        //Room must exist in rooms list
-        let room_data = self
-            .rooms
-            .iter()
-            .find(|room| room.id == room_id)
-            .ok_or_else(|| anyhow!("Unknown room id"))?;
+        //charlie's client stopped, the game goes on without him
+        server_data.clock.advance(400);
+        assert_eq!(
+            server_data.forfeit_disconnected_players(),
+            vec![game_data.id]
+        );
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(game_data.forfeited, vec![players[2].id]);
+        assert_eq!(game_data.status, GameStatus::Running);
 
-        if !room_data
-            .players
-            .iter()
-            .any(|player| player.id == player_id)
-        {
-            return Err(anyhow!("Player not in the room"));
-        }
+        //then bob's, alice is the last one left
+        server_data.clock.advance(700);
+        server_data.heartbeat(players[0].id).unwrap();
+        assert_eq!(
+            server_data.forfeit_disconnected_players(),
+            vec![game_data.id]
+        );
+        let game_data = server_data
+            .get_game_data(players[0].id, game_data.id)
+            .unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, Some(players[0].id));
 
-        Ok(room_data.clone())
+        //bots are never heard from, they don't disconnect
+        let dave = server_data
+            .create_player_with_region("Dave".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(dave.id, "bot room".to_string(), None)
+            .unwrap();
+        server_data
+            .seat_bots(room_data.id, 1, BotStrategy::Random)
+            .unwrap();
+        let game_data = server_data.launch_room(dave.id, room_data.id).unwrap();
+        server_data.clock.advance(5000);
+        server_data.heartbeat(dave.id).unwrap();
+        assert!(server_data.forfeit_disconnected_players().is_empty());
+        assert_eq!(
+            server_data
+                .get_game_data(dave.id, game_data.id)
+                .unwrap()
+                .status,
+            GameStatus::Running
+        );
     }
 
-    pub fn launch_room(&mut self, player_id: PlayerId, room_id: RoomId) -> Result<GameData> {
-        self.players
-            .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
+    #[test]
+    fn test_rematch() {
+        let mut server_data = ServerData::default();
+        let settings = GameSettings {
+            end_condition: EndCondition::FirstToScore(1),
+            ..GameSettings::default()
+        };
 
-        let (room_index, room_data) = self
-            .rooms
-            .iter()
-            .enumerate()
-            .find(|(_, room)| room.id == room_id)
-            .ok_or_else(|| anyhow!("Unknown room id"))?;
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], settings.clone());
+        let (alice, bob) = (players[0].id, players[1].id);
+        let carol = server_data
+            .create_player_with_region("Carol".to_string(), None)
+            .unwrap()
+            .id;
 
-        let (player_index, _) = room_data
-            .players
-            .iter()
-            .enumerate()
-            .find(|(_, player)| player.id == player_id)
-            .ok_or_else(|| anyhow!("Player not in the room"))?;
+        assert_eq!(
+            server_data.rematch(alice, game_data.id).unwrap_err(),
+            GameError::GameStillRunning
+        );
+        server_data
+            .play_batch(
+                game_data.id,
+                vec![(alice, ActionKind::Rock), (bob, ActionKind::Scissors)],
+            )
+            .unwrap();
+        assert_eq!(
+            server_data.rematch(carol, game_data.id).unwrap_err(),
+            GameError::NotInGame
+        );
 
-        if player_index != 0 {
-            return Err(anyhow!("Player is not the host"));
-        }
+        let rematch = server_data.rematch(alice, game_data.id).unwrap();
+        assert_ne!(rematch.id, game_data.id);
+        assert_eq!(rematch.status, GameStatus::Running);
+        assert_eq!(rematch.settings, settings);
+        assert_eq!(
+            rematch
+                .players
+                .iter()
+                .map(|(player, score)| (player.id, *score))
+                .collect_vec(),
+            vec![(alice, 0), (bob, 0)]
+        );
+        assert!(rematch.round_history.is_empty());
 
-        if room_data.players.len() != room_data.settings.player_count {
-            return Err(anyhow!("Room must be full to launch the game"));
-        }
+        //Bob asking too joins the same rematch
+        assert_eq!(
+            server_data.rematch(bob, game_data.id).unwrap().id,
+            rematch.id
+        );
 
-        let game_data = self.create_game(room_data.clone());
-        self.games.push(game_data.clone());
+        //The bot went away with its game
+        let room_data = server_data
+            .create_room(carol, "test room".to_string(), Some(settings))
+            .unwrap();
+        let game_data = server_data
+            .launch_room_with_bots(carol, room_data.id, BotStrategy::AlwaysRock)
+            .unwrap();
+        server_data
+            .play_round(carol, game_data.id, ActionKind::Paper)
+            .unwrap();
+        assert_eq!(
+            server_data.rematch(carol, game_data.id).unwrap_err(),
+            GameError::PlayerGone
+        );
+    }
 
-        self.rooms.remove(room_index);
+    #[test]
+    fn test_featured_game() {
+        let mut server_data = ServerData::default();
+        assert_eq!(
+            server_data.featured_game().unwrap_err(),
+            GameError::NoRunningGame
+        );
 
-        Ok(game_data)
-    }
+        let settings = GameSettings {
+            end_condition: EndCondition::FirstToScore(1),
+            ..GameSettings::default()
+        };
 
-    pub fn get_game_data(&self, player_id: PlayerId, game_id: GameId) -> Result<GameData> {
-        self.players
-            .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
+        //Alice wins a first game against Bob
+        let (players, game_data) =
+            launch_game(&mut server_data, &["Alice", "Bob"], settings.clone());
+        let (alice, bob) = (players[0].id, players[1].id);
+        server_data
+            .play_batch(
+                game_data.id,
+                vec![(alice, ActionKind::Rock), (bob, ActionKind::Scissors)],
+            )
+            .unwrap();
 
-        let game_data = self
-            .games
-            .iter()
-            .find(|game| game.id == game_id)
-            .ok_or_else(|| anyhow!("Unknown game id"))?;
+        let (_, newcomers_game) =
+            launch_game(&mut server_data, &["Carol", "Dave"], settings.clone());
+        assert_eq!(server_data.featured_game().unwrap().id, newcomers_game.id);
 
-        if !game_data
-            .players
-            .iter()
-            .any(|(player, _)| player.id == player_id)
-        {
-            return Err(anyhow!("Player not in the game"));
-        }
+        //Their rematch involves more wins than the game of the newcomers
+        let room_data = server_data
+            .create_room(alice, "rematch".to_string(), Some(settings))
+            .unwrap();
+        server_data
+            .join_room_with_password(bob, room_data.id, None)
+            .unwrap();
+        server_data.set_ready(alice, room_data.id, true).unwrap();
+        server_data.set_ready(bob, room_data.id, true).unwrap();
+        let rematch = server_data.launch_room(alice, room_data.id).unwrap();
 
-        Ok(game_data.clone())
+        assert_eq!(server_data.featured_game().unwrap().id, rematch.id);
     }
 
-    pub fn play_round(
-        &mut self,
-        player_id: PlayerId,
-        game_id: GameId,
-        action: ActionKind,
-    ) -> Result<GameData> {
-        self.players
-            .iter()
-            .find(|player| player.id == player_id)
-            .ok_or_else(|| anyhow!("Unknown player id"))?;
-
-        let game_data = self
-            .games
-            .iter_mut()
-            .find(|game| game.id == game_id)
-            .ok_or_else(|| anyhow!("Unknown game id"))?;
+    #[test]
+    fn test_max_rounds_cap() {
+        let mut server_data = ServerData::default();
+        let (players, game_data) = launch_game(
+            &mut server_data,
+            &["Alice", "Bob"],
+            GameSettings {
+                end_condition: EndCondition::FirstToScore(1),
+                max_rounds: 5,
+                ..GameSettings::default()
+            },
+        );
+        let (alice, bob) = (players[0].id, players[1].id);
 
-        if !game_data
-            .players
-            .iter()
-            .any(|(player, _)| player.id == player_id)
-        {
-            return Err(anyhow!("Player not in the game"));
+        //Nobody ever scores, the game would go on forever without the cap
+        for round in 1..=5 {
+            let game_data = server_data
+                .play_batch(
+                    game_data.id,
+                    vec![(alice, ActionKind::Rock), (bob, ActionKind::Rock)],
+                )
+                .unwrap();
+            assert_eq!(game_data.round_history.len(), round);
         }
 
-        if game_data.status != GameStatus::Running {
-            return Err(anyhow!("Game is not running anymore"));
-        }
+        let game_data = server_data.get_game_data(alice, game_data.id).unwrap();
+        assert_eq!(game_data.status, GameStatus::Ended);
+        assert_eq!(game_data.winner, None);
+        assert_eq!(game_data.winners, vec![alice, bob]);
+        assert_eq!(
+            server_data
+                .play_round(alice, game_data.id, ActionKind::Rock)
+                .unwrap_err(),
+            GameError::GameNotRunning
+        );
+    }
 
-        game_data
-            .current_round
-            .inputs
-            .entry(player_id)
-            .and_modify(|e| *e = action.clone())
-            .or_insert(action);
+    #[test]
+    fn test_spectators() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
 
-        if !game_data
-            .players
-            .iter()
-            .any(|(player_data, _)| !game_data.current_round.inputs.contains_key(&player_data.id))
-        {
-            let mut round_results = Vec::new();
-
-            let mut keys = game_data.current_round.inputs.keys();
-            while let Some(first_player_id) = keys.next() {
-                let iter = keys.clone();
-
-                let p1_tuple = (
-                    *first_player_id,
-                    game_data.current_round.inputs.get(first_player_id).unwrap(),
-                );
-
-                for second_player_id in iter {
-                    let p2_tuple = (
-                        *second_player_id,
-                        game_data
-                            .current_round
-                            .inputs
-                            .get(second_player_id)
-                            .unwrap(),
-                    );
-
-                    let round_result = match (p1_tuple.1, p2_tuple.1) {
-                        (ActionKind::Rock, ActionKind::Rock)
-                        | (ActionKind::Paper, ActionKind::Paper)
-                        | (ActionKind::Scissors, ActionKind::Scissors) => RoundResult::Draw,
-                        (ActionKind::Rock, ActionKind::Paper)
-                        | (ActionKind::Paper, ActionKind::Scissors)
-                        | (ActionKind::Scissors, ActionKind::Rock) => {
-                            game_data
-                                .players
-                                .iter_mut()
-                                .for_each(|(player_data, score)| {
-                                    if player_data.id == p2_tuple.0 {
-                                        *score += 1
-                                    }
-                                });
-                            RoundResult::Winner(p2_tuple.0)
-                        }
-                        (ActionKind::Rock, ActionKind::Scissors)
-                        | (ActionKind::Paper, ActionKind::Rock)
-                        | (ActionKind::Scissors, ActionKind::Paper) => {
-                            game_data
-                                .players
-                                .iter_mut()
-                                .for_each(|(player_data, score)| {
-                                    if player_data.id == p1_tuple.0 {
-                                        *score += 1
-                                    }
-                                });
-                            RoundResult::Winner(p1_tuple.0)
-                        }
-                    };
+        //The room is full, it still accepts spectators
+        assert_eq!(
+            server_data.join_room_with_password(charlie.id, room_data.id, None),
+            Err(GameError::RoomFull)
+        );
+        let room_data = server_data
+            .join_room_as_spectator(charlie.id, room_data.id, None)
+            .unwrap();
+        assert_eq!(room_data.players, vec![alice.clone(), bob.clone()]);
+        assert_eq!(room_data.spectators, vec![charlie.clone()]);
+        assert_eq!(
+            server_data.join_room_as_spectator(charlie.id, room_data.id, None),
+            Err(GameError::AlreadyInRoom)
+        );
+        assert_eq!(
+            server_data.join_room_as_spectator(bob.id, room_data.id, None),
+            Err(GameError::AlreadyInRoom)
+        );
+        assert!(server_data.get_room_data(charlie.id, room_data.id).is_ok());
 
-                    round_results.push(round_result.clone());
-                }
-            }
-            game_data.current_round.result = Some(round_results.to_vec());
+        //A spectator is never the host
+        assert_eq!(
+            server_data.launch_room(charlie.id, room_data.id).err(),
+            Some(GameError::NotInRoom)
+        );
 
+        server_data.set_ready(alice.id, room_data.id, true).unwrap();
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        let game_data = server_data.launch_room(alice.id, room_data.id).unwrap();
+        assert_eq!(
             game_data
-                .round_history
-                .push(game_data.current_round.clone());
-            game_data.current_round = RoundData::default();
-
-            match game_data.settings.end_condition {
-                EndCondition::TotalRounds(x) => {
-                    if game_data.round_history.len() == x {
-                        game_data.status = GameStatus::Ended;
-                    }
-                }
-                EndCondition::FirstToScore(x) => {
-                    if let Some((_, max)) = game_data
-                        .players
-                        .iter()
-                        .max_by(|(_, a_score), (_, b_score)| a_score.cmp(b_score))
-                    {
-                        if *max == x {
-                            game_data.status = GameStatus::Ended;
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(game_data.clone())
+                .players
+                .iter()
+                .map(|(player, _)| player.id)
+                .collect_vec(),
+            vec![alice.id, bob.id]
+        );
     }
 
-    pub fn get_rooms_list(&self) -> Vec<RoomData> {
-        self.rooms.to_vec()
-    }
-}
+    #[test]
+    fn test_spectator_leaves_or_takes_a_seat() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
 
-#[cfg(test)]
-mod tests {
+        server_data
+            .join_room_as_spectator(bob.id, room_data.id, None)
+            .unwrap();
+        server_data.leave_room(bob.id, room_data.id).unwrap();
+        let room_data = server_data.get_room_data(alice.id, room_data.id).unwrap();
+        assert!(room_data.spectators.is_empty());
+        //Spectators don't keep a seat reserved
+        assert!(room_data.reserved_seats.is_empty());
 
-    // Note this useful idiom: importing names from outer (for mod tests) scope.
-    use super::*;
+        server_data
+            .join_room_as_spectator(bob.id, room_data.id, None)
+            .unwrap();
+        let (room_data, became_full) = server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        assert!(became_full);
+        assert_eq!(room_data.players, vec![alice, bob]);
+        assert!(room_data.spectators.is_empty());
+    }
 
     #[test]
-    fn test_create_player() {
+    fn test_kick_player() {
         let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(
+                alice.id,
+                "test room".to_string(),
+                Some(GameSettings {
+                    player_count: 3,
+                    ..GameSettings::default()
+                }),
+            )
+            .unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .unwrap();
 
         assert_eq!(
-            server_data
-                .create_player_with_name("Alice".to_string())
-                .unwrap(),
-            PlayerData {
-                id: 0,
-                name: "Alice".to_string()
-            }
+            server_data.kick_player(bob.id, room_data.id, charlie.id),
+            Err(GameError::NotHost)
+        );
+        assert_eq!(
+            server_data.kick_player(alice.id, room_data.id, alice.id),
+            Err(GameError::NotInRoom)
         );
 
+        let room_data = server_data
+            .kick_player(alice.id, room_data.id, bob.id)
+            .unwrap();
         assert_eq!(
-            server_data
-                .create_player_with_name("Bob".to_string())
-                .unwrap(),
-            PlayerData {
-                id: 1,
-                name: "Bob".to_string()
-            }
+            room_data
+                .players
+                .iter()
+                .map(|player| player.id)
+                .collect_vec(),
+            vec![alice.id, charlie.id]
+        );
+        assert_eq!(
+            server_data.kick_player(alice.id, room_data.id, bob.id),
+            Err(GameError::NotInRoom)
         );
+    }
 
-        assert!(
-            server_data
-                .create_player_with_name("Bob".to_string())
-                .is_err(),
-            "Bob already exists"
+    #[test]
+    fn test_rename_player() {
+        let mut server_data = ServerData::default();
+        let alice = server_data
+            .create_player_with_region("Alice".to_string(), None)
+            .unwrap();
+        let bob = server_data
+            .create_player_with_region("Bob".to_string(), None)
+            .unwrap();
+        let room_data = server_data
+            .create_room(alice.id, "test room".to_string(), None)
+            .unwrap();
+
+        assert_eq!(
+            server_data.rename_player(alice.id, "Bob".to_string()),
+            Err(GameError::NameTaken)
         );
+
+        let renamed = server_data
+            .rename_player(alice.id, "Alicia".to_string())
+            .unwrap();
+        assert_eq!(renamed.name, "Alicia");
+        assert_eq!(server_data.players[&alice.id].name, "Alicia");
+
+        //The room keeps its own copy of the player
+        let rooms = server_data.get_rooms_filtered(None);
+        assert_eq!(rooms[0].players[0].name, "Alicia");
+        //So do its spectators
+        let charlie = server_data
+            .create_player_with_region("Charlie".to_string(), None)
+            .unwrap();
+        server_data
+            .join_room_as_spectator(charlie.id, room_data.id, None)
+            .unwrap();
+        server_data
+            .rename_player(charlie.id, "Charles".to_string())
+            .unwrap();
+        let rooms = server_data.get_rooms_filtered(None);
+        assert_eq!(rooms[0].spectators[0].name, "Charles");
+
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
+        server_data.set_ready(alice.id, room_data.id, true).unwrap();
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        let game_data = server_data.launch_room(alice.id, room_data.id).unwrap();
+        let robert = server_data
+            .rename_player(bob.id, "Robert".to_string())
+            .unwrap();
+
+        //Live games are left to rename_in_game
+        let game = server_data.game_handle(game_data.id).unwrap();
+        assert_eq!(ServerData::lock_game(&game).players[1].0.name, "Bob");
+        assert!(ServerData::rename_in_game(
+            &mut ServerData::lock_game(&game),
+            &robert
+        ));
+        let renamed_game = server_data.get_game_data(bob.id, game_data.id).unwrap();
+        assert_eq!(renamed_game.players[1].0.name, "Robert");
+        assert!(renamed_game.version > game_data.version);
+        //Games the player isn't in are left as they are
+        let stranger = PlayerData {
+            id: 99,
+            ..robert.clone()
+        };
+        assert!(!ServerData::rename_in_game(
+            &mut ServerData::lock_game(&game),
+            &stranger
+        ));
     }
 
     #[test]
@@ -415,13 +5873,13 @@ mod tests {
         let mut server_data = ServerData::default();
 
         let alice = server_data
-            .create_player_with_name("Alice".to_string())
+            .create_player_with_region("Alice".to_string(), None)
             .unwrap();
         let bob = server_data
-            .create_player_with_name("Bob".to_string())
+            .create_player_with_region("Bob".to_string(), None)
             .unwrap();
         let charlie = server_data
-            .create_player_with_name("Charlie".to_string())
+            .create_player_with_region("Charlie".to_string(), None)
             .unwrap();
 
         let room_data = server_data
@@ -429,9 +5887,8 @@ mod tests {
                 alice.id,
                 "test room".to_string(),
                 Some(GameSettings {
-                    kind: GameKind::RockPaperScissors,
-                    player_count: 2,
                     end_condition: EndCondition::FirstToScore(2),
+                    ..GameSettings::default()
                 }),
             )
             .unwrap();
@@ -439,10 +5896,14 @@ mod tests {
         assert_eq!(server_data.get_rooms_list().len(), 1);
 
         //bob joins the room, which becomes full
-        server_data.join_room(bob.id, room_data.id).unwrap();
+        server_data
+            .join_room_with_password(bob.id, room_data.id, None)
+            .unwrap();
 
         //charlie can't the room,as it is full
-        assert!(server_data.join_room(charlie.id, room_data.id).is_err());
+        assert!(server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .is_err());
 
         //alice leaves the room, the host is now the second one who joined, which is bob
         server_data.leave_room(alice.id, room_data.id).unwrap();
@@ -454,12 +5915,20 @@ mod tests {
         assert!(server_data.launch_room(bob.id, room_data.id).is_err());
 
         //charlie joins the room, which becomes full again
-        server_data.join_room(charlie.id, room_data.id).unwrap();
+        server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .unwrap();
 
         //charlie can't join the room twice, as he is already inside
-        assert!(server_data.join_room(charlie.id, room_data.id).is_err());
+        assert!(server_data
+            .join_room_with_password(charlie.id, room_data.id, None)
+            .is_err());
 
         //bob can now launch the game, as the room is full
+        server_data.set_ready(bob.id, room_data.id, true).unwrap();
+        server_data
+            .set_ready(charlie.id, room_data.id, true)
+            .unwrap();
         let game_data = server_data.launch_room(bob.id, room_data.id).unwrap();
 
         //There are no more rooms available, as the game got launched
@@ -507,7 +5976,10 @@ mod tests {
                 .unwrap()
                 .get(0)
                 .unwrap()),
-            RoundResult::Winner(bob.id)
+            RoundResult::Duel {
+                players: [bob.id, charlie.id],
+                winner: Some(bob.id)
+            }
         );
 
         //bob plays Scissors
@@ -532,7 +6004,10 @@ mod tests {
                 .unwrap()
                 .get(0)
                 .unwrap()),
-            RoundResult::Draw
+            RoundResult::Duel {
+                players: [bob.id, charlie.id],
+                winner: None
+            }
         );
 
         //bob plays Scissors
@@ -557,7 +6032,10 @@ mod tests {
                 .unwrap()
                 .get(0)
                 .unwrap()),
-            RoundResult::Winner(bob.id)
+            RoundResult::Duel {
+                players: [bob.id, charlie.id],
+                winner: Some(bob.id)
+            }
         );
 
         assert_eq!(game_data.status, GameStatus::Ended);
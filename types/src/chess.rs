@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+//Files and ranks both go from 0 to 7, the white pieces start on ranks 0 and 1
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub struct Square {
+    pub file: u8,
+    pub rank: u8,
+}
+
+impl Square {
+    pub fn new(file: u8, rank: u8) -> Self {
+        Self { file, rank }
+    }
+
+    fn is_on_board(&self) -> bool {
+        self.file < 8 && self.rank < 8
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ChessColor {
+    White,
+    Black,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChessPiece {
+    pub kind: PieceKind,
+    pub color: ChessColor,
+}
+
+//How a piece moves on its own, without looking at checks. Castling, en
+//passant and promotion are not handled yet
+pub trait PieceMovement {
+    fn can_move(&self, board: &ChessBoard, from: Square, to: Square) -> bool;
+}
+
+impl PieceMovement for ChessPiece {
+    fn can_move(&self, board: &ChessBoard, from: Square, to: Square) -> bool {
+        let file_delta = to.file as i8 - from.file as i8;
+        let rank_delta = to.rank as i8 - from.rank as i8;
+        let is_straight = file_delta == 0 || rank_delta == 0;
+        let is_diagonal = file_delta.abs() == rank_delta.abs();
+
+        match self.kind {
+            PieceKind::Pawn => {
+                let (forward, start_rank) = match self.color {
+                    ChessColor::White => (1, 1),
+                    ChessColor::Black => (-1, 6),
+                };
+                let target = board.piece_at(to);
+
+                match (file_delta.abs(), rank_delta) {
+                    (0, delta) if delta == forward => target.is_none(),
+                    (0, delta) if delta == 2 * forward => {
+                        from.rank == start_rank && target.is_none() && board.is_path_clear(from, to)
+                    }
+                    (1, delta) if delta == forward => {
+                        target.is_some_and(|piece| piece.color != self.color)
+                    }
+                    _ => false,
+                }
+            }
+            PieceKind::Knight => matches!((file_delta.abs(), rank_delta.abs()), (1, 2) | (2, 1)),
+            PieceKind::Bishop => is_diagonal && board.is_path_clear(from, to),
+            PieceKind::Rook => is_straight && board.is_path_clear(from, to),
+            PieceKind::Queen => (is_straight || is_diagonal) && board.is_path_clear(from, to),
+            PieceKind::King => file_delta.abs() <= 1 && rank_delta.abs() <= 1,
+        }
+    }
+}
+
+//Indexed by rank then file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChessBoard {
+    pub squares: [[Option<ChessPiece>; 8]; 8],
+}
+
+impl ChessBoard {
+    pub fn empty() -> Self {
+        Self {
+            squares: [[None; 8]; 8],
+        }
+    }
+
+    pub fn starting() -> Self {
+        let back_rank = [
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+        ];
+
+        let mut board = Self::empty();
+        for (file, kind) in back_rank.into_iter().enumerate() {
+            let file = file as u8;
+            for (color, pieces_rank, pawns_rank) in
+                [(ChessColor::White, 0, 1), (ChessColor::Black, 7, 6)]
+            {
+                board.place(Square::new(file, pieces_rank), ChessPiece { kind, color });
+                board.place(
+                    Square::new(file, pawns_rank),
+                    ChessPiece {
+                        kind: PieceKind::Pawn,
+                        color,
+                    },
+                );
+            }
+        }
+
+        board
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<ChessPiece> {
+        self.squares[square.rank as usize][square.file as usize]
+    }
+
+    pub fn place(&mut self, square: Square, piece: ChessPiece) {
+        self.squares[square.rank as usize][square.file as usize] = Some(piece);
+    }
+
+    //Whether every square strictly between the two is empty, for moves along
+    //a line or a diagonal
+    fn is_path_clear(&self, from: Square, to: Square) -> bool {
+        let file_step = (to.file as i8 - from.file as i8).signum();
+        let rank_step = (to.rank as i8 - from.rank as i8).signum();
+
+        let mut square = from;
+        loop {
+            square = Square::new(
+                (square.file as i8 + file_step) as u8,
+                (square.rank as i8 + rank_step) as u8,
+            );
+            if square == to {
+                return true;
+            }
+            if self.piece_at(square).is_some() {
+                return false;
+            }
+        }
+    }
+
+    pub fn is_legal_move(&self, color: ChessColor, from: Square, to: Square) -> bool {
+        if !from.is_on_board() || !to.is_on_board() || from == to {
+            return false;
+        }
+
+        let Some(piece) = self.piece_at(from) else {
+            return false;
+        };
+        if piece.color != color {
+            return false;
+        }
+        if self
+            .piece_at(to)
+            .is_some_and(|target| target.color == color)
+        {
+            return false;
+        }
+
+        piece.can_move(self, from, to)
+    }
+
+    //The move is expected to be legal
+    pub fn apply_move(&mut self, from: Square, to: Square) {
+        self.squares[to.rank as usize][to.file as usize] =
+            self.squares[from.rank as usize][from.file as usize].take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_piece_movement() {
+        let board = ChessBoard::starting();
+
+        //pawns move one or two squares forward from their starting rank
+        assert!(board.is_legal_move(ChessColor::White, Square::new(4, 1), Square::new(4, 3)));
+        assert!(board.is_legal_move(ChessColor::Black, Square::new(4, 6), Square::new(4, 5)));
+        assert!(!board.is_legal_move(ChessColor::White, Square::new(4, 1), Square::new(4, 4)));
+        assert!(!board.is_legal_move(ChessColor::White, Square::new(4, 1), Square::new(5, 2)));
+
+        //pieces of the other player can't be moved
+        assert!(!board.is_legal_move(ChessColor::White, Square::new(4, 6), Square::new(4, 5)));
+
+        //knights jump over pieces, rooks can't
+        assert!(board.is_legal_move(ChessColor::White, Square::new(1, 0), Square::new(2, 2)));
+        assert!(!board.is_legal_move(ChessColor::White, Square::new(0, 0), Square::new(0, 3)));
+
+        let mut board = ChessBoard::empty();
+        let rook = ChessPiece {
+            kind: PieceKind::Rook,
+            color: ChessColor::White,
+        };
+        board.place(Square::new(0, 0), rook);
+        assert!(board.is_legal_move(ChessColor::White, Square::new(0, 0), Square::new(0, 7)));
+        assert!(board.is_legal_move(ChessColor::White, Square::new(0, 0), Square::new(7, 0)));
+        assert!(!board.is_legal_move(ChessColor::White, Square::new(0, 0), Square::new(1, 1)));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+//Error codes shared by the server and its clients, serialized as
+//SCREAMING_SNAKE_CASE strings so they stay stable across renames of the messages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GameError {
+    NameTaken,
+    UnknownPlayer,
+    UnknownRoom,
+    UnknownGame,
+    AlreadyInRoom,
+    RoomFull,
+    AlreadyLeftRoom,
+    SelfMerge,
+    GameKindMismatch,
+    NotEnoughSeats,
+    NotInRoom,
+    NotHost,
+    RoomNotFull,
+    NotInGame,
+    AlreadyInGame,
+    NoMoves,
+    GameNotRunning,
+    ActionNotAvailable,
+    ActionLockedIn,
+    ActionCooldown,
+    PlayerEliminated,
+    StaleVersion,
+    TooManyRooms,
+    InvalidWinTable,
+    SelfFriend,
+    EphemeralPlayersDisabled,
+    NoHumanPlayer,
+    GameStillRunning,
+    NotFound,
+    IllegalMove,
+    NotYourTurn,
+    NotEnoughPlayers,
+    UnknownRound,
+    InvalidToken,
+    WrongPassword,
+    NoRunningGame,
+    InvalidRequest,
+    PlayerGone,
+    InvalidPlayerCount,
+    PlayersNotReady,
+    PlayerForfeited,
+    GamePaused,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            GameError::NameTaken => "This name is already taken",
+            GameError::UnknownPlayer => "Unknown player id",
+            GameError::UnknownRoom => "Unknown room id",
+            GameError::UnknownGame => "Unknown game id",
+            GameError::AlreadyInRoom => "Player already in the room",
+            GameError::RoomFull => "Room full",
+            GameError::AlreadyLeftRoom => "Player already left the room",
+            GameError::SelfMerge => "Can't merge a room with itself",
+            GameError::GameKindMismatch => "Rooms are not playing the same game",
+            GameError::NotEnoughSeats => "Not enough seats to merge the rooms",
+            GameError::NotInRoom => "Player not in the room",
+            GameError::NotHost => "Player is not the host",
+            GameError::RoomNotFull => "Room must be full to launch the game",
+            GameError::NotInGame => "Player not in the game",
+            GameError::AlreadyInGame => "Player is in the game",
+            GameError::NoMoves => "No move to play",
+            GameError::GameNotRunning => "Game is not running anymore",
+            GameError::ActionNotAvailable => "Action not available in this game",
+            GameError::ActionLockedIn => "Action already locked in for this round",
+            GameError::ActionCooldown => "Action changed too quickly",
+            GameError::PlayerEliminated => "Player has been eliminated",
+            GameError::StaleVersion => "Game changed since this version",
+            GameError::TooManyRooms => "Player is already hosting too many rooms",
+            GameError::InvalidWinTable => "Win table is not well-formed",
+            GameError::SelfFriend => "Players can't be their own friend",
+            GameError::EphemeralPlayersDisabled => "Guest players are disabled",
+            GameError::NoHumanPlayer => "At least one human player is needed",
+            GameError::GameStillRunning => "Game is not finished yet",
+            GameError::NotFound => "Unknown route",
+            GameError::IllegalMove => "Illegal move",
+            GameError::NotYourTurn => "Not this player's turn",
+            GameError::NotEnoughPlayers => "A game needs at least two players",
+            GameError::UnknownRound => "No such round in this game",
+            GameError::InvalidToken => "Missing or invalid session token",
+            GameError::WrongPassword => "Wrong room password",
+            GameError::NoRunningGame => "No game is running right now",
+            GameError::InvalidRequest => "Malformed request",
+            GameError::PlayerGone => "A player of that game is gone",
+            GameError::InvalidPlayerCount => "Player count not allowed for this game",
+            GameError::PlayersNotReady => "Every player must be ready to launch the game",
+            GameError::PlayerForfeited => "Player has forfeited the game",
+            GameError::GamePaused => "Game is paused",
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+impl std::error::Error for GameError {}
+
+//Body of every error response of the API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ErrorResponse {
+    pub code: GameError,
+    pub message: String,
+}
+
+impl From<GameError> for ErrorResponse {
+    fn from(value: GameError) -> Self {
+        Self {
+            code: value,
+            message: value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_game_error_round_trip() {
+        let errors = [
+            GameError::NameTaken,
+            GameError::UnknownPlayer,
+            GameError::UnknownRoom,
+            GameError::UnknownGame,
+            GameError::AlreadyInRoom,
+            GameError::RoomFull,
+            GameError::AlreadyLeftRoom,
+            GameError::SelfMerge,
+            GameError::GameKindMismatch,
+            GameError::NotEnoughSeats,
+            GameError::NotInRoom,
+            GameError::NotHost,
+            GameError::RoomNotFull,
+            GameError::NotInGame,
+            GameError::AlreadyInGame,
+            GameError::NoMoves,
+            GameError::GameNotRunning,
+            GameError::GamePaused,
+            GameError::ActionNotAvailable,
+            GameError::ActionLockedIn,
+            GameError::ActionCooldown,
+            GameError::PlayerEliminated,
+            GameError::PlayerForfeited,
+            GameError::StaleVersion,
+            GameError::TooManyRooms,
+            GameError::InvalidWinTable,
+            GameError::SelfFriend,
+            GameError::EphemeralPlayersDisabled,
+            GameError::NoHumanPlayer,
+            GameError::GameStillRunning,
+            GameError::NotFound,
+            GameError::IllegalMove,
+            GameError::NotYourTurn,
+            GameError::NotEnoughPlayers,
+            GameError::UnknownRound,
+            GameError::InvalidToken,
+            GameError::WrongPassword,
+            GameError::NoRunningGame,
+            GameError::InvalidRequest,
+            GameError::PlayerGone,
+            GameError::InvalidPlayerCount,
+            GameError::PlayersNotReady,
+        ];
+
+        for error in errors {
+            let serialized = serde_json::to_string(&error).unwrap();
+            assert_eq!(
+                serde_json::from_str::<GameError>(&serialized).unwrap(),
+                error
+            );
+        }
+
+        assert_eq!(
+            serde_json::to_string(&GameError::UnknownPlayer).unwrap(),
+            r#""UNKNOWN_PLAYER""#
+        );
+    }
+}
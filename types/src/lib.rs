@@ -1,18 +1,91 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, fmt, ops::RangeInclusive, time::Duration};
 
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DisplayFromStr};
+#[cfg(feature = "serde_with")]
+use serde_with::{As, DisplayFromStr};
+
+pub mod chess;
+#[cfg(not(feature = "serde_with"))]
+mod display_from_str;
+pub mod error;
+pub mod logic;
+
+use chess::{ChessBoard, Square};
+use error::GameError;
+use logic::{ChessLogic, CustomLogic, GameLogic, RpsLogic, RpslsLogic};
 
 pub type RoomId = i32;
 pub type GameId = i32;
 pub type PlayerId = i32;
+pub type Timestamp = u64;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum GameKind {
+    #[default]
     RockPaperScissors,
+    //Rock paper scissors played with a custom win table, each (winner, loser)
+    //pair listed. Meant for unbalanced variants with a dominant action
+    Custom {
+        wins: Vec<(ActionKind, ActionKind)>,
+    },
+    RockPaperScissorsLizardSpock,
+    //Only piece movement is checked for now, there is no check nor mate and
+    //rounds always end in a draw
+    Chess,
+}
+
+impl GameKind {
+    //The only place a game kind is matched on to find its rules
+    pub fn logic(&self) -> Box<dyn GameLogic + '_> {
+        match self {
+            GameKind::RockPaperScissors => Box::new(RpsLogic),
+            GameKind::Custom { wins } => Box::new(CustomLogic { wins }),
+            GameKind::RockPaperScissorsLizardSpock => Box::new(RpslsLogic),
+            GameKind::Chess => Box::new(ChessLogic),
+        }
+    }
+
+    pub fn available_actions(&self) -> Vec<ActionKind> {
+        self.logic().available_actions()
+    }
+
+    //Used when a room is created without saying how its game ends
+    pub fn default_end_condition(&self) -> EndCondition {
+        match self {
+            GameKind::RockPaperScissors
+            | GameKind::Custom { .. }
+            | GameKind::RockPaperScissorsLizardSpock => EndCondition::FirstToScore(3),
+            //Nobody ever scores in chess for now, a single game is stopped
+            //after that many moves of each player
+            GameKind::Chess => EndCondition::TotalRounds(50),
+        }
+    }
+
+    pub fn beats(&self, action: &ActionKind, other: &ActionKind) -> bool {
+        self.logic().beats(action, other)
+    }
+
+    pub fn player_counts(&self) -> RangeInclusive<usize> {
+        self.logic().player_counts()
+    }
+
+    //A win table can't have an action beating itself, nor two actions
+    //beating each other
+    pub fn validate(&self) -> Result<(), GameError> {
+        if let GameKind::Custom { wins } = self {
+            if wins
+                .iter()
+                .any(|(winner, loser)| winner == loser || self.beats(loser, winner))
+            {
+                return Err(GameError::InvalidWinTable);
+            }
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum EndCondition {
     TotalRounds(usize),
     FirstToScore(usize),
@@ -21,6 +94,8 @@ pub enum EndCondition {
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum GameStatus {
     Running,
+    //No moves are taken until a player resumes the game
+    Paused,
     Ended,
 }
 
@@ -29,41 +104,433 @@ pub enum ActionKind {
     Rock,
     Paper,
     Scissors,
+    Lizard,
+    Spock,
+    Move { from: Square, to: Square },
+}
+
+//Each (winner, loser) pair of Rock Paper Scissors Lizard Spock. Only the
+//first three are left when playing plain Rock Paper Scissors
+const WINS: [(ActionKind, ActionKind); 10] = [
+    (ActionKind::Rock, ActionKind::Scissors),
+    (ActionKind::Paper, ActionKind::Rock),
+    (ActionKind::Scissors, ActionKind::Paper),
+    (ActionKind::Rock, ActionKind::Lizard),
+    (ActionKind::Lizard, ActionKind::Spock),
+    (ActionKind::Spock, ActionKind::Scissors),
+    (ActionKind::Scissors, ActionKind::Lizard),
+    (ActionKind::Lizard, ActionKind::Paper),
+    (ActionKind::Paper, ActionKind::Spock),
+    (ActionKind::Spock, ActionKind::Rock),
+];
+
+impl ActionKind {
+    pub fn beats(&self, other: &ActionKind) -> bool {
+        WINS.iter()
+            .any(|(winner, loser)| winner == self && loser == other)
+    }
+}
+
+//How bots filling empty seats pick their action
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum BotStrategy {
+    //Goes through the available actions one round after the other
+    #[default]
+    Cycle,
+    AlwaysRock,
+    //Plays back the actions recorded from a finished game, in order. Only
+    //the server builds it, from a recording, clients can't ask for it
+    #[serde(skip_deserializing)]
+    Replay(Vec<ActionKind>),
+    Random,
+    //Plays what beats the most of the actions thrown by the others in the
+    //last round, cycles until there is one
+    CounterLast,
+}
+
+impl BotStrategy {
+    //Whether every action the strategy can pick is available in that kind
+    //of game, bots don't go through the checks players' actions go through
+    pub fn is_available_in(&self, kind: &GameKind) -> bool {
+        let available_actions = kind.available_actions();
+        match self {
+            BotStrategy::AlwaysRock => available_actions.contains(&ActionKind::Rock),
+            BotStrategy::Replay(actions) => {
+                !actions.is_empty()
+                    && actions
+                        .iter()
+                        .all(|action| available_actions.contains(action))
+            }
+            BotStrategy::Cycle | BotStrategy::Random | BotStrategy::CounterLast => {
+                !available_actions.is_empty()
+            }
+        }
+    }
+
+    //The randomness comes from the caller, this crate has no source of it
+    pub fn pick_action(&self, game_data: &GameData, bot_id: PlayerId, random: usize) -> ActionKind {
+        let available_actions = game_data.settings.kind.available_actions();
+        let round = game_data.round_history.len();
+        let cycle = || available_actions[round % available_actions.len()].clone();
+
+        match self {
+            BotStrategy::Cycle => cycle(),
+            BotStrategy::AlwaysRock => ActionKind::Rock,
+            BotStrategy::Replay(actions) => actions
+                .iter()
+                .cycle()
+                .nth(round)
+                .cloned()
+                .unwrap_or_else(cycle),
+            BotStrategy::Random => available_actions[random % available_actions.len()].clone(),
+            BotStrategy::CounterLast => {
+                let last_actions = game_data
+                    .round_history
+                    .last()
+                    .map(|last_round| {
+                        last_round
+                            .inputs
+                            .iter()
+                            .filter(|(player_id, _)| **player_id != bot_id)
+                            .map(|(_, action)| action)
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                let beaten_count = |action: &ActionKind| {
+                    last_actions
+                        .iter()
+                        .filter(|last_action| game_data.settings.kind.beats(action, last_action))
+                        .count()
+                };
+
+                //The first of the actions beating the most, in available order
+                available_actions
+                    .iter()
+                    .rev()
+                    .max_by_key(|action| beaten_count(action))
+                    .filter(|action| beaten_count(action) > 0)
+                    .cloned()
+                    .unwrap_or_else(cycle)
+            }
+        }
+    }
+}
+
+//How the actions thrown in a round are turned into points
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum ScoringMode {
+    //Every pair of players is matched, each win scores a point
+    #[default]
+    Pairwise,
+    //The single most played action among those beating another one scores
+    //a point for each of its throwers
+    Majority,
+    //Players whose action only loses are eliminated
+    Survival,
 }
 
-#[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+//What happens when a game would end with several players sharing the best
+//score
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum TieBreak {
+    //The game ends without a single winner, every tied player is listed
+    //among the winners
+    #[default]
+    Draw,
+    //Extra rounds are played until one of them breaks the tie, the game
+    //ends as a draw if it is still tied after that many of them
+    SuddenDeath(usize),
+}
+
+//Values are sent as strings so the settings can be flattened in query
+//strings. serde_with is an optional dependency, the client can leave it out
+//and fall back on the display_from_str module, which has the same format.
+//Fields left out take their value from GameSettings::default
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct GameSettings {
     pub kind: GameKind,
-    #[serde_as(as = "DisplayFromStr")]
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<DisplayFromStr>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str"))]
     pub player_count: usize,
     pub end_condition: EndCondition,
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<DisplayFromStr>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str"))]
+    pub allow_action_change: bool,
+    //Only tell how many players are still to play, not who they are
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<DisplayFromStr>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str"))]
+    pub hide_waiting_players: bool,
+    //Minimum delay before a player can change their action again
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<Option<DisplayFromStr>>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str::option"))]
+    pub action_cooldown_ms: Option<Timestamp>,
+    //Rounds still waiting on someone after that long are resolved without
+    //them, and they lose the round
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<Option<DisplayFromStr>>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str::option"))]
+    pub round_timeout_secs: Option<u64>,
+    //Spectators see the actions of the current round once they were played
+    //that long ago, like a broadcast delay. They never see them otherwise
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<Option<DisplayFromStr>>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str::option"))]
+    pub spectator_delay_secs: Option<u64>,
+    pub scoring_mode: ScoringMode,
+    pub tie_break: TieBreak,
+    //Safety net for games which would never end otherwise, like a
+    //FirstToScore game where every round is a draw. The game ends after that
+    //many rounds whatever its end condition, tie breaks included
+    #[cfg_attr(feature = "serde_with", serde(with = "As::<DisplayFromStr>"))]
+    #[cfg_attr(not(feature = "serde_with"), serde(with = "display_from_str"))]
+    pub max_rounds: usize,
 }
 
-#[derive(Debug, Clone)]
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            kind: GameKind::default(),
+            player_count: 2,
+            end_condition: GameKind::default().default_end_condition(),
+            allow_action_change: true,
+            hide_waiting_players: false,
+            action_cooldown_ms: None,
+            round_timeout_secs: None,
+            spectator_delay_secs: None,
+            scoring_mode: ScoringMode::default(),
+            tie_break: TieBreak::default(),
+            max_rounds: 100,
+        }
+    }
+}
+
+//Rough time a player takes to pick an action
+const ESTIMATED_ROUND_DURATION: Duration = Duration::from_secs(10);
+
+impl GameSettings {
+    pub fn validate(&self) -> Result<(), GameError> {
+        self.kind.validate()?;
+
+        if !self.kind.player_counts().contains(&self.player_count) {
+            return Err(GameError::InvalidPlayerCount);
+        }
+
+        Ok(())
+    }
+
+    pub fn estimated_duration(&self) -> Duration {
+        let estimated_rounds = match self.end_condition {
+            EndCondition::TotalRounds(rounds) => rounds,
+            //Points are spread among the players, so the more players the
+            //longer it takes for one of them to reach the target
+            EndCondition::FirstToScore(score) => score * self.player_count,
+        };
+
+        ESTIMATED_ROUND_DURATION * estimated_rounds as u32
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RoomData {
     pub id: RoomId,
     pub name: String,
     pub settings: GameSettings,
+    //The one able to launch the room. Always the first of the players, when
+    //they leave the next human in line takes over
+    pub host_id: PlayerId,
     pub players: Vec<PlayerData>,
+    //Watching the room without a seat, they don't count toward player_count
+    //and are left out of the game once it is launched
+    #[serde(default)]
+    pub spectators: Vec<PlayerData>,
+    pub reserved_seats: Vec<(PlayerId, Timestamp)>,
+    //Players who confirmed they are ready, cleared whenever a player joins.
+    //The host launching the room and bots never have to
+    #[serde(default)]
+    pub ready: Vec<PlayerId>,
+    #[serde(default)]
+    pub visibility: RoomVisibility,
+    //Needed to join the room, only ever sent by the players joining it
+    #[serde(default)]
+    pub password: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum RoomVisibility {
+    #[default]
+    Public,
+    //Only listed to the host and the players in the host's friends list
+    FriendsOnly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PlayerData {
     pub id: PlayerId,
     pub name: String,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub friends: Vec<PlayerId>,
 }
 
+//A resolved round is a list of these, each naming the players it is about.
+//
+//Pairwise rounds are decided by summed pairwise wins: every pair of players
+//duels, with one Duel per pair ordered by player ids, and every duel won is
+//worth a point. Players who timed out lose their duel against everyone who
+//played. Several players can score in the same round, a game ending with
+//them tied is settled by its TieBreak.
+//
+//Majority rounds have a Winner per thrower of the majority action, Survival
+//rounds an Eliminated per player knocked out. They are a single Draw when
+//nobody scored or was knocked out
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub enum RoundResult {
     Draw,
     Winner(PlayerId),
+    Eliminated(PlayerId),
+    //Lower id first, no winner when neither action beats the other
+    Duel {
+        players: [PlayerId; 2],
+        winner: Option<PlayerId>,
+    },
 }
 
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct RoundData {
-    pub inputs: HashMap<PlayerId, ActionKind>,
+    //Ordered by player id so that serialized rounds are stable
+    pub inputs: BTreeMap<PlayerId, ActionKind>,
+    pub submitted_at: BTreeMap<PlayerId, Timestamp>,
     pub result: Option<Vec<RoundResult>>,
+    //How the round went for each player, filled in once resolved
+    pub per_player: BTreeMap<PlayerId, PlayerRoundOutcome>,
+    //How much each player's score changed when the round was resolved
+    pub score_deltas: BTreeMap<PlayerId, i64>,
+    //Suggested order for the client to reveal actions in, earliest submission first
+    pub reveal_order: Vec<PlayerId>,
+    //Players who didn't act before the round timed out
+    pub timed_out: Vec<PlayerId>,
+    //When the game was launched or the previous round was resolved
+    pub started_at: Timestamp,
+    pub resolved_at: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash)]
+pub enum PlayerRoundOutcome {
+    Won,
+    Lost,
+    Drew,
+    //Won against some players and lost or drew against others
+    Mixed,
+}
+
+//What is keeping a given player from launching a given room
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum LaunchReadiness {
+    Ready,
+    UnknownPlayer,
+    UnknownRoom,
+    NotInRoom,
+    NotHost,
+    NotFull {
+        players: usize,
+        required: usize,
+        recovery: Vec<LaunchRecovery>,
+    },
+    //How many players still have to confirm they are ready
+    NotReady {
+        waiting: usize,
+    },
+}
+
+//What the host can do to launch a room that is still missing players
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum LaunchRecovery {
+    //Launch right away through /room/launch_with_bots
+    FillWithBots { bots: usize },
+    //Lower the player count to the seated players, through /room/reduce_count
+    ReduceCount { player_count: usize },
+}
+
+impl LaunchReadiness {
+    pub fn into_result(self) -> Result<(), GameError> {
+        match self {
+            LaunchReadiness::Ready => Ok(()),
+            LaunchReadiness::UnknownPlayer => Err(GameError::UnknownPlayer),
+            LaunchReadiness::UnknownRoom => Err(GameError::UnknownRoom),
+            LaunchReadiness::NotInRoom => Err(GameError::NotInRoom),
+            LaunchReadiness::NotHost => Err(GameError::NotHost),
+            LaunchReadiness::NotFull { .. } => Err(GameError::RoomNotFull),
+            LaunchReadiness::NotReady { .. } => Err(GameError::PlayersNotReady),
+        }
+    }
+}
+
+impl fmt::Display for LaunchReadiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchReadiness::Ready => write!(f, "Ready to launch"),
+            LaunchReadiness::UnknownPlayer => write!(f, "Unknown player id"),
+            LaunchReadiness::UnknownRoom => write!(f, "Unknown room id"),
+            LaunchReadiness::NotInRoom => write!(f, "Player not in the room"),
+            LaunchReadiness::NotHost => write!(f, "Player is not the host"),
+            LaunchReadiness::NotFull { .. } => write!(f, "Room must be full to launch the game"),
+            LaunchReadiness::NotReady { waiting } => {
+                write!(f, "Waiting for {waiting} players to be ready")
+            }
+        }
+    }
+}
+
+//How a player played across all their finished games
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PlayerAnalytics {
+    pub games_played: usize,
+    pub games_won: usize,
+    pub win_rate: f64,
+    //Most played first, ties in the order the actions were first played
+    pub action_counts: Vec<(ActionKind, usize)>,
+    pub favorite_action: Option<ActionKind>,
+}
+
+//Outcomes of every game a player finished. A game shared between tied
+//leaders is a draw for them and a loss for the others
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PlayerStats {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub games_played: usize,
+}
+
+impl PlayerStats {
+    pub fn win_rate(&self) -> f64 {
+        match self.games_played {
+            0 => 0.0,
+            games_played => self.wins as f64 / games_played as f64,
+        }
+    }
+}
+
+//Moves a player sent to /game/play, to spot clients sending garbage
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct MoveStats {
+    pub accepted: usize,
+    pub rejected: usize,
+}
+
+impl MoveStats {
+    pub fn rejection_rate(&self) -> f64 {
+        match self.accepted + self.rejected {
+            0 => 0.0,
+            total => self.rejected as f64 / total as f64,
+        }
+    }
+}
+
+//Things that happened to a player while they were not the one acting
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Notification {
+    PromotedToHost { room_id: RoomId },
 }
 
 #[derive(Debug, Clone)]
@@ -74,87 +541,216 @@ pub struct GameData {
     pub current_round: RoundData,
     pub round_history: Vec<RoundData>,
     pub status: GameStatus,
+    pub winner: Option<PlayerId>,
+    //Everyone sharing the best result once the game ended, only the winner
+    //unless the game ended tied
+    pub winners: Vec<PlayerId>,
+    pub observers: Vec<PlayerData>,
+    //Players knocked out of a Survival game, they don't play anymore
+    pub eliminated: Vec<PlayerId>,
+    //Players who gave up, they don't play anymore and can't win the game
+    pub forfeited: Vec<PlayerId>,
+    //Bumped on every change, used to answer polls with 304 Not Modified
+    pub version: u64,
+    //Only set for Chess games
+    pub board: Option<ChessBoard>,
+    pub round_started_at: Timestamp,
+    pub paused_at: Option<Timestamp>,
+    pub ended_at: Option<Timestamp>,
+    //Extra rounds played so far to break a tie
+    pub tie_break_rounds: usize,
+    //Idempotency key of the last action each player got played
+    pub submission_keys: BTreeMap<PlayerId, String>,
+}
+
+impl GameData {
+    pub fn remaining_players(&self) -> impl Iterator<Item = &PlayerData> {
+        self.players
+            .iter()
+            .map(|(player, _)| player)
+            .filter(|player| {
+                !self.eliminated.contains(&player.id) && !self.forfeited.contains(&player.id)
+            })
+    }
+
+    //How many more actions the current round needs to be resolved, players
+    //knocked out of the game aren't waited for
+    pub fn required_submissions_remaining(&self) -> usize {
+        self.remaining_players()
+            .filter(|player| !self.current_round.inputs.contains_key(&player.id))
+            .count()
+    }
+
+    //The player with the strictly highest score, or the last one standing in
+    //Survival games
+    pub fn winner(&self) -> Option<PlayerId> {
+        match self.leaders().as_slice() {
+            [winner] => Some(*winner),
+            _ => None,
+        }
+    }
+
+    //Every player sharing the highest score, or still standing in Survival
+    //games, in seating order. Players who forfeited are left out
+    pub fn leaders(&self) -> Vec<PlayerId> {
+        if self.settings.scoring_mode == ScoringMode::Survival {
+            return self.remaining_players().map(|player| player.id).collect();
+        }
+
+        let scores = self
+            .players
+            .iter()
+            .filter(|(player, _)| !self.forfeited.contains(&player.id));
+        let Some(best_score) = scores.clone().map(|(_, score)| *score).max() else {
+            return vec![];
+        };
+
+        scores
+            .filter(|(_, score)| *score == best_score)
+            .map(|(player, _)| player.id)
+            .collect()
+    }
 }
 
 pub mod net {
 
+    use std::collections::BTreeMap;
+
     use serde::{Deserialize, Serialize};
 
     use crate::{
-        ActionKind, GameData, GameId, GameSettings, GameStatus, PlayerData, PlayerId, RoomData,
-        RoomId, RoundData,
+        chess::ChessBoard, ActionKind, BotStrategy, EndCondition, GameData, GameId, GameKind,
+        GameSettings, GameStatus, LaunchReadiness, MoveStats, Notification, PlayerData, PlayerId,
+        PlayerStats, RoomData, RoomId, RoomVisibility, RoundData, Timestamp,
     };
 
     #[derive(Serialize, Debug, Clone)]
     pub struct PlayerFullData {
         id: PlayerId,
         name: String,
+        region: Option<String>,
+        friends: Vec<PlayerId>,
     }
 
     impl From<PlayerData> for PlayerFullData {
         fn from(value: PlayerData) -> Self {
+            let PlayerData {
+                id,
+                name,
+                region,
+                friends,
+            } = value;
+
             Self {
-                id: value.id,
-                name: value.name,
+                id,
+                name,
+                region,
+                friends,
             }
         }
     }
 
-    #[derive(Serialize, Debug, Clone)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct PlayerPublicData {
-        name: String,
+        pub name: String,
     }
 
     impl From<PlayerData> for PlayerPublicData {
         fn from(value: PlayerData) -> Self {
-            Self { name: value.name }
+            //The id is private to the player it belongs to
+            let PlayerData {
+                id: _,
+                name,
+                region: _,
+                friends: _,
+            } = value;
+
+            Self { name }
         }
     }
 
     #[derive(Debug, Deserialize)]
     pub struct NewPlayerQuery {
         pub name: String,
+        pub region: Option<String>,
     }
 
+    //The session token is only ever sent here, the player then proves who
+    //they are with it as a bearer token on every mutating request
     #[derive(Serialize, Debug, Clone)]
     pub struct NewPlayerResponse {
         pub player: PlayerFullData,
+        pub token: String,
     }
 
-    impl From<PlayerData> for NewPlayerResponse {
-        fn from(value: PlayerData) -> Self {
+    impl NewPlayerResponse {
+        pub fn new(player_data: PlayerData, token: String) -> Self {
             Self {
-                player: PlayerFullData::from(value),
+                player: PlayerFullData::from(player_data),
+                token,
             }
         }
     }
 
-    #[derive(Debug, Serialize)]
+    #[derive(Debug, Serialize, Deserialize)]
     pub struct RoomPublicData {
-        id: RoomId,
-        name: String,
-        settings: GameSettings,
-        players: Vec<PlayerPublicData>,
+        pub id: RoomId,
+        pub name: String,
+        pub settings: GameSettings,
+        //Ids are private, the host is given as one of the players. Only None
+        //for a room without players, which is never kept
+        pub host: Option<PlayerPublicData>,
+        pub players: Vec<PlayerPublicData>,
+        pub spectators: Vec<PlayerPublicData>,
+        //The players who confirmed they are ready, in seat order
+        pub ready: Vec<PlayerPublicData>,
+        pub estimated_duration_secs: u64,
+        pub visibility: RoomVisibility,
+        pub has_password: bool,
     }
 
     impl From<RoomData> for RoomPublicData {
         fn from(value: RoomData) -> Self {
+            let RoomData {
+                id,
+                name,
+                settings,
+                host_id,
+                players,
+                spectators,
+                reserved_seats: _,
+                ready,
+                visibility,
+                password,
+            } = value;
+
             Self {
-                id: value.id,
-                settings: value.settings,
-                players: value
-                    .players
-                    .into_iter()
+                id,
+                name,
+                visibility,
+                has_password: password.is_some(),
+                estimated_duration_secs: settings.estimated_duration().as_secs(),
+                settings,
+                host: players
+                    .iter()
+                    .find(|player| player.id == host_id)
+                    .cloned()
+                    .map(PlayerPublicData::from),
+                ready: players
+                    .iter()
+                    .filter(|player| ready.contains(&player.id))
+                    .cloned()
                     .map(PlayerPublicData::from)
                     .collect(),
-                name: value.name,
+                players: players.into_iter().map(PlayerPublicData::from).collect(),
+                spectators: spectators.into_iter().map(PlayerPublicData::from).collect(),
             }
         }
     }
 
-    #[derive(Debug, Serialize)]
+    #[derive(Debug, Serialize, Deserialize)]
     pub struct RoomsListResponse {
-        rooms: Vec<RoomPublicData>,
+        pub rooms: Vec<RoomPublicData>,
     }
 
     impl From<Vec<RoomData>> for RoomsListResponse {
@@ -165,12 +761,262 @@ pub mod net {
         }
     }
 
+    //What anyone may know about a game, the actions of the current round
+    //stay hidden
+    #[derive(Debug, Serialize)]
+    pub struct GamePublicData {
+        pub id: GameId,
+        pub settings: GameSettings,
+        pub players: Vec<PlayerPublicData>,
+        pub status: GameStatus,
+    }
+
+    impl From<GameData> for GamePublicData {
+        fn from(value: GameData) -> Self {
+            //Only what is needed to list the game, scores and rounds are
+            //fetched with the game itself
+            let GameData {
+                id,
+                settings,
+                players,
+                current_round: _,
+                round_history: _,
+                status,
+                winner: _,
+                winners: _,
+                observers: _,
+                eliminated: _,
+                forfeited: _,
+                version: _,
+                board: _,
+                round_started_at: _,
+                paused_at: _,
+                ended_at: _,
+                tie_break_rounds: _,
+                submission_keys: _,
+            } = value;
+
+            Self {
+                id,
+                settings,
+                players: players
+                    .into_iter()
+                    .map(|(player, _)| PlayerPublicData::from(player))
+                    .collect(),
+                status,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct GamesListResponse {
+        pub games: Vec<GamePublicData>,
+    }
+
+    impl From<Vec<GameData>> for GamesListResponse {
+        fn from(value: Vec<GameData>) -> Self {
+            Self {
+                games: value.into_iter().map(GamePublicData::from).collect(),
+            }
+        }
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct NewRoomQuery {
         pub player_id: PlayerId,
         pub room_name: String,
+        //Every setting left out takes its default value
         #[serde(flatten)]
-        pub settings: Option<GameSettings>,
+        pub settings: GameSettings,
+        //Read apart from the other settings, when left out it depends on the
+        //kind of game
+        #[serde(default)]
+        pub end_condition: Option<EndCondition>,
+        #[serde(default)]
+        pub visibility: RoomVisibility,
+        #[serde(default)]
+        pub password: Option<String>,
+        //Seats taken by bots right away, next to the host
+        #[serde(default)]
+        pub bot_count: usize,
+        #[serde(default)]
+        pub bot_strategy: BotStrategy,
+    }
+
+    impl NewRoomQuery {
+        pub fn game_settings(&self) -> GameSettings {
+            GameSettings {
+                end_condition: self
+                    .end_condition
+                    .clone()
+                    .unwrap_or_else(|| self.settings.kind.default_end_condition()),
+                ..self.settings.clone()
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct LeaderboardResponse {
+        pub entries: Vec<LeaderboardEntry>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct LeaderboardEntry {
+        pub name: String,
+        pub wins: usize,
+        pub games_played: usize,
+    }
+
+    impl From<Vec<(PlayerData, PlayerStats)>> for LeaderboardResponse {
+        fn from(value: Vec<(PlayerData, PlayerStats)>) -> Self {
+            Self {
+                entries: value
+                    .into_iter()
+                    .map(|(player, stats)| {
+                        //Ids stay private, like in PlayerPublicData
+                        let PlayerData {
+                            id: _,
+                            name,
+                            region: _,
+                            friends: _,
+                        } = player;
+                        let PlayerStats {
+                            wins,
+                            losses: _,
+                            draws: _,
+                            games_played,
+                        } = stats;
+
+                        LeaderboardEntry {
+                            name,
+                            wins,
+                            games_played,
+                        }
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LeaderboardQuery {
+        //How many players to list, the server picks when left out
+        #[serde(default)]
+        pub limit: Option<usize>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct SuspiciousPlayersResponse {
+        pub players: Vec<(PlayerPublicData, MoveStats)>,
+    }
+
+    impl From<Vec<(PlayerData, MoveStats)>> for SuspiciousPlayersResponse {
+        fn from(value: Vec<(PlayerData, MoveStats)>) -> Self {
+            Self {
+                players: value
+                    .into_iter()
+                    .map(|(player, stats)| (PlayerPublicData::from(player), stats))
+                    .collect(),
+            }
+        }
+    }
+
+    //Browsers can't set headers on a WebSocket, the token comes in the query
+    #[derive(Debug, Deserialize)]
+    pub struct PresenceQuery {
+        pub player_id: PlayerId,
+        pub token: String,
+    }
+
+    //Same as PresenceQuery, for the live updates of a game
+    #[derive(Debug, Deserialize)]
+    pub struct GameSocketQuery {
+        pub player_id: PlayerId,
+        pub game_id: GameId,
+        pub token: String,
+    }
+
+    //Sent over the presence WebSocket, players are named since their ids
+    //are private
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub enum PresenceEvent {
+        Online { name: String },
+        Offline { name: String },
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct HeartbeatQuery {
+        pub player_id: PlayerId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct AnalyticsQuery {
+        pub player_id: PlayerId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct PlayerStatsQuery {
+        pub player_id: PlayerId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct NotificationsQuery {
+        pub player_id: PlayerId,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct NotificationsResponse {
+        pub notifications: Vec<Notification>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct MetricsResponse {
+        pub total_players: usize,
+        pub active_players: usize,
+        pub rooms: usize,
+        pub games: usize,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RoomsListQuery {
+        pub player_id: Option<PlayerId>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GamesListQuery {
+        //Ended games are left out unless asked for
+        #[serde(default)]
+        pub include_ended: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RenamePlayerQuery {
+        pub player_id: PlayerId,
+        pub name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct FriendQuery {
+        pub player_id: PlayerId,
+        pub friend_id: PlayerId,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct FriendsResponse {
+        pub friends: Vec<PlayerId>,
+    }
+
+    impl From<PlayerData> for FriendsResponse {
+        fn from(value: PlayerData) -> Self {
+            let PlayerData {
+                id: _,
+                name: _,
+                region: _,
+                friends,
+            } = value;
+
+            Self { friends }
+        }
     }
 
     #[derive(Debug, Serialize)]
@@ -190,26 +1036,112 @@ pub mod net {
     pub struct JoinGetLeaveRoomQuery {
         pub player_id: PlayerId,
         pub room_id: RoomId,
+        //Only checked when joining a room
+        #[serde(default)]
+        pub password: Option<String>,
+    }
+
+    //Sent by the host, target_id is the player to remove
+    #[derive(Debug, Deserialize)]
+    pub struct KickPlayerQuery {
+        pub player_id: PlayerId,
+        pub room_id: RoomId,
+        pub target_id: PlayerId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RoomGameQuery {
+        pub room_id: RoomId,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct RoomGameResponse {
+        pub game_id: GameId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct MergeRoomsQuery {
+        pub source_room_id: RoomId,
+        pub target_room_id: RoomId,
     }
 
     #[derive(Debug, Serialize)]
     pub struct JoinGetRoomResponse {
         pub room: RoomPublicData,
+        pub became_full: bool,
+        //Whether the player asking is the host, who is the one able to launch
+        pub is_host: bool,
     }
 
     impl From<RoomData> for JoinGetRoomResponse {
         fn from(value: RoomData) -> Self {
             Self {
                 room: RoomPublicData::from(value),
+                became_full: false,
+                is_host: false,
+            }
+        }
+    }
+
+    impl JoinGetRoomResponse {
+        pub fn for_player(room: RoomData, became_full: bool, player_id: PlayerId) -> Self {
+            let is_host = room.host_id == player_id;
+
+            Self {
+                room: RoomPublicData::from(room),
+                became_full,
+                is_host,
             }
         }
     }
 
+    #[derive(Debug, Serialize)]
+    pub struct LaunchStatusResponse {
+        pub readiness: LaunchReadiness,
+    }
+
+    impl From<LaunchReadiness> for LaunchStatusResponse {
+        fn from(value: LaunchReadiness) -> Self {
+            Self { readiness: value }
+        }
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct LaunchGameQuery {
         pub player_id: PlayerId,
         pub room_id: RoomId,
     }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ReplayPracticeQuery {
+        pub player_id: PlayerId,
+        pub source_game_id: GameId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ReadyQuery {
+        pub player_id: PlayerId,
+        pub room_id: RoomId,
+        pub ready: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RematchQuery {
+        pub player_id: PlayerId,
+        pub game_id: GameId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LaunchWithBotsQuery {
+        pub player_id: PlayerId,
+        pub room_id: RoomId,
+        #[serde(default)]
+        pub strategy: BotStrategy,
+    }
+
+    //Every collection in here comes out in join order or in player id order,
+    //never in the order players happened to act in, so that two identical
+    //games serialize the same way
     #[derive(Debug, Serialize)]
     pub struct LaunchGetGameResponse {
         id: GameId,
@@ -217,34 +1149,167 @@ pub mod net {
         status: GameStatus,
         players: Vec<(PlayerPublicData, usize)>,
         waiting_for_players: Vec<PlayerPublicData>,
+        waiting_count: usize,
         round_history: Vec<RoundData>,
+        winner: Option<PlayerPublicData>,
+        winners: Vec<PlayerPublicData>,
+        eliminated: Vec<PlayerId>,
+        forfeited: Vec<PlayerId>,
+        board: Option<ChessBoard>,
+        round_started_at: Timestamp,
+        //What the requesting player played in the current round, the other
+        //players' actions stay hidden until the round is resolved
+        your_current_action: Option<ActionKind>,
+        //Same scores as `players`, keyed by player name. Only sent when
+        //asked for, for clients that would rather not deal with tuples
+        #[serde(skip_serializing_if = "Option::is_none")]
+        scores: Option<BTreeMap<String, usize>>,
     }
 
+    impl LaunchGetGameResponse {
+        pub fn with_scores_map(mut self, scores_as_map: bool) -> Self {
+            self.scores = scores_as_map.then(|| {
+                self.players
+                    .iter()
+                    .map(|(player, score)| (player.name.clone(), *score))
+                    .collect()
+            });
+            self
+        }
+    }
+
+    //The view of someone who doesn't play in the game
     impl From<GameData> for LaunchGetGameResponse {
         fn from(value: GameData) -> Self {
-            let mut waiting_for_players: Vec<PlayerData> = value
-                .players
-                .iter()
-                .map(|(player, _)| player.clone())
-                .collect();
+            Self::for_player(value, None)
+        }
+    }
+
+    impl LaunchGetGameResponse {
+        pub fn for_player(value: GameData, player_id: Option<PlayerId>) -> Self {
+            let waiting_count = value.required_submissions_remaining();
+
+            //current_round is only used to know who we are waiting for, and
+            //to give the requesting player their own action back
+            let GameData {
+                id,
+                settings,
+                players,
+                current_round,
+                round_history,
+                status,
+                winner,
+                winners,
+                //observers are only listed to spectators
+                observers: _,
+                eliminated,
+                forfeited,
+                //sent as the ETag header of /game/data instead
+                version: _,
+                board,
+                round_started_at,
+                //only used to resume the round timer where it stopped
+                paused_at: _,
+                //only used to know when the game can be archived
+                ended_at: _,
+                //the extra rounds are already in round_history
+                tie_break_rounds: _,
+                //private to each player
+                submission_keys: _,
+            } = value;
+
+            let public_data = |player_id: PlayerId| {
+                players
+                    .iter()
+                    .find(|(player, _)| player.id == player_id)
+                    .map(|(player, _)| PlayerPublicData::from(player.clone()))
+            };
+            let winner = winner.and_then(public_data);
+            let winners = winners.into_iter().filter_map(public_data).collect();
+
+            let mut waiting_for_players: Vec<PlayerData> =
+                players.iter().map(|(player, _)| player.clone()).collect();
+
+            waiting_for_players.retain(|player_data| {
+                !current_round.inputs.contains_key(&player_data.id)
+                    && !eliminated.contains(&player_data.id)
+                    && !forfeited.contains(&player_data.id)
+            });
 
-            waiting_for_players
-                .retain(|player_data| !value.current_round.inputs.contains_key(&player_data.id));
+            if settings.hide_waiting_players {
+                waiting_for_players.clear();
+            }
+
+            let your_current_action =
+                player_id.and_then(|player_id| current_round.inputs.get(&player_id).cloned());
 
             Self {
-                id: value.id,
-                players: value
-                    .players
+                id,
+                players: players
                     .into_iter()
                     .map(|(player, score)| (PlayerPublicData::from(player), score))
                     .collect(),
-                settings: value.settings,
-                round_history: value.round_history.to_vec(),
+                settings,
+                round_history,
                 waiting_for_players: waiting_for_players
                     .into_iter()
                     .map(PlayerPublicData::from)
                     .collect(),
-                status: value.status,
+                waiting_count,
+                status,
+                winner,
+                winners,
+                eliminated,
+                forfeited,
+                board,
+                round_started_at,
+                your_current_action,
+                scores: None,
+            }
+        }
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct SpectateGameResponse {
+        #[serde(flatten)]
+        game: LaunchGetGameResponse,
+        observers: Vec<PlayerPublicData>,
+        //Current round actions old enough to get past the spectator delay
+        delayed_actions: BTreeMap<PlayerId, ActionKind>,
+    }
+
+    impl GameData {
+        //Same view as the players get, so the current round inputs are
+        //stripped the same way, plus the list of observers and the actions
+        //let through by the spectator delay
+        pub fn to_spectator_response(&self, now: Timestamp) -> SpectateGameResponse {
+            let delayed_actions = match self.settings.spectator_delay_secs {
+                Some(delay_secs) => {
+                    self.current_round
+                        .inputs
+                        .iter()
+                        .filter(|(player_id, _)| {
+                            self.current_round.submitted_at.get(player_id).is_some_and(
+                                |submitted_at| {
+                                    now.saturating_sub(*submitted_at) >= delay_secs * 1000
+                                },
+                            )
+                        })
+                        .map(|(player_id, action)| (*player_id, action.clone()))
+                        .collect()
+                }
+                None => BTreeMap::new(),
+            };
+
+            SpectateGameResponse {
+                game: LaunchGetGameResponse::from(self.clone()),
+                observers: self
+                    .observers
+                    .iter()
+                    .cloned()
+                    .map(PlayerPublicData::from)
+                    .collect(),
+                delayed_actions,
             }
         }
     }
@@ -253,6 +1318,125 @@ pub mod net {
     pub struct GetGameQuery {
         pub player_id: PlayerId,
         pub game_id: GameId,
+        #[serde(default)]
+        pub scores_as_map: bool,
+    }
+
+    //Without a player, the game is watched anonymously and the spectator
+    //isn't listed among the observers
+    #[derive(Debug, Deserialize)]
+    pub struct SpectateGameQuery {
+        #[serde(default)]
+        pub player_id: Option<PlayerId>,
+        pub game_id: GameId,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GamesBatchQuery {
+        pub player_id: PlayerId,
+        pub game_ids: Vec<GameId>,
+    }
+
+    //Enough to list a game without fetching all of it
+    #[derive(Debug, Serialize)]
+    pub struct GameSummary {
+        pub id: GameId,
+        pub kind: GameKind,
+        pub status: GameStatus,
+        pub players: Vec<(PlayerPublicData, usize)>,
+        pub winner: Option<PlayerPublicData>,
+        pub rounds_played: usize,
+    }
+
+    impl From<GameData> for GameSummary {
+        fn from(value: GameData) -> Self {
+            //The rounds themselves are fetched with the game
+            let GameData {
+                id,
+                settings,
+                players,
+                current_round: _,
+                round_history,
+                status,
+                winner,
+                winners: _,
+                observers: _,
+                eliminated: _,
+                forfeited: _,
+                version: _,
+                board: _,
+                round_started_at: _,
+                paused_at: _,
+                ended_at: _,
+                tie_break_rounds: _,
+                submission_keys: _,
+            } = value;
+
+            let winner = winner.and_then(|winner_id| {
+                players
+                    .iter()
+                    .find(|(player, _)| player.id == winner_id)
+                    .map(|(player, _)| PlayerPublicData::from(player.clone()))
+            });
+
+            Self {
+                id,
+                kind: settings.kind,
+                status,
+                players: players
+                    .into_iter()
+                    .map(|(player, score)| (PlayerPublicData::from(player), score))
+                    .collect(),
+                winner,
+                rounds_played: round_history.len(),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct GamesBatchResponse {
+        pub games: Vec<GameSummary>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GetRoundQuery {
+        pub player_id: PlayerId,
+        pub game_id: GameId,
+        pub round_index: usize,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct LegalActionsResponse {
+        pub actions: Vec<ActionKind>,
+    }
+
+    impl From<Vec<ActionKind>> for LegalActionsResponse {
+        fn from(value: Vec<ActionKind>) -> Self {
+            Self { actions: value }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct MatchmakingQuery {
+        pub player_id: PlayerId,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct MatchmakingResponse {
+        pub game: Option<LaunchGetGameResponse>,
+    }
+
+    impl From<Option<GameData>> for MatchmakingResponse {
+        fn from(value: Option<GameData>) -> Self {
+            Self {
+                game: value.map(LaunchGetGameResponse::from),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct MatchmakingEstimateResponse {
+        pub estimated_wait_ms: Timestamp,
     }
 
     #[derive(Debug, Deserialize)]
@@ -260,5 +1444,453 @@ pub mod net {
         pub player_id: PlayerId,
         pub game_id: GameId,
         pub action: ActionKind,
+        //Version of the game the client last saw, the play is rejected if
+        //the game changed since
+        pub expected_version: Option<u64>,
+        //Picked by the client for each action. A client which lost its
+        //connection can send the action again with the same key, it is
+        //only played once
+        pub idempotency_key: Option<String>,
+        #[serde(default)]
+        pub scores_as_map: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct PlayerMove {
+        pub player_id: PlayerId,
+        //Moves of several players are sent at once, each comes with the
+        //session token of its player
+        pub token: String,
+        pub action: ActionKind,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct PlayBatchQuery {
+        pub game_id: GameId,
+        pub moves: Vec<PlayerMove>,
+    }
+
+    #[cfg(test)]
+    mod tests {
+
+        use super::*;
+        use crate::tests::{game_with_players, player};
+
+        #[test]
+        fn test_default_end_condition_per_kind() {
+            let kinds = [
+                GameKind::RockPaperScissors,
+                GameKind::Custom { wins: vec![] },
+                GameKind::RockPaperScissorsLizardSpock,
+                GameKind::Chess,
+            ];
+
+            for kind in kinds {
+                let query: NewRoomQuery = serde_json::from_value(serde_json::json!({
+                    "player_id": 0,
+                    "room_name": "test room",
+                    "kind": kind,
+                }))
+                .unwrap();
+                assert_eq!(
+                    query.game_settings().end_condition,
+                    kind.default_end_condition()
+                );
+
+                //An end condition which is given is kept
+                let query: NewRoomQuery = serde_json::from_value(serde_json::json!({
+                    "player_id": 0,
+                    "room_name": "test room",
+                    "kind": kind,
+                    "end_condition": { "TotalRounds": 7 },
+                }))
+                .unwrap();
+                assert_eq!(
+                    query.game_settings().end_condition,
+                    EndCondition::TotalRounds(7)
+                );
+            }
+
+            assert_eq!(
+                GameSettings::default().end_condition,
+                EndCondition::FirstToScore(3)
+            );
+        }
+
+        //The From impls above destructure their source with an exhaustive
+        //pattern (no `..`), so adding a field to RoomData, PlayerData or
+        //GameData fails to compile until the conversion decides what to do
+        //with it. This test pins down what each conversion currently keeps.
+        #[test]
+        fn test_conversions_keep_every_field() {
+            let alice = player(7, "Alice");
+
+            let room = RoomPublicData::from(RoomData {
+                id: 3,
+                name: "test room".to_string(),
+                settings: GameSettings::default(),
+                host_id: alice.id,
+                players: vec![alice.clone()],
+                spectators: vec![],
+                reserved_seats: vec![],
+                ready: vec![],
+                visibility: RoomVisibility::Public,
+                password: None,
+            });
+
+            let room = serde_json::to_value(room).unwrap();
+            let room = room.as_object().unwrap();
+            assert_eq!(
+                room.keys().collect::<Vec<_>>(),
+                vec![
+                    "estimated_duration_secs",
+                    "has_password",
+                    "host",
+                    "id",
+                    "name",
+                    "players",
+                    "ready",
+                    "settings",
+                    "spectators",
+                    "visibility"
+                ]
+            );
+            assert_eq!(room["id"], 3);
+            assert_eq!(room["name"], "test room");
+
+            let player = serde_json::to_value(PlayerFullData::from(alice.clone())).unwrap();
+            assert_eq!(player["id"], 7);
+            assert_eq!(player["name"], "Alice");
+
+            //the public view deliberately drops the id
+            let player = serde_json::to_value(PlayerPublicData::from(alice)).unwrap();
+            assert!(player.get("id").is_none());
+            assert_eq!(player["name"], "Alice");
+        }
+
+        #[test]
+        fn test_room_response_tells_the_host() {
+            let room = RoomData {
+                id: 0,
+                name: "test room".to_string(),
+                settings: GameSettings::default(),
+                host_id: 0,
+                players: vec![player(0, "Alice"), player(1, "Bob")],
+                spectators: vec![],
+                reserved_seats: vec![],
+                ready: vec![],
+                visibility: RoomVisibility::Public,
+                password: None,
+            };
+
+            assert!(JoinGetRoomResponse::for_player(room.clone(), true, 0).is_host);
+            assert!(!JoinGetRoomResponse::for_player(room.clone(), true, 1).is_host);
+            assert!(!JoinGetRoomResponse::from(room).is_host);
+        }
+
+        #[test]
+        fn test_current_round_inputs_never_leak() {
+            let mut game_data = game_with_players(&["Alice", "Bob", "Charlie"]);
+            for (player_id, action) in [
+                (0, crate::ActionKind::Rock),
+                (1, crate::ActionKind::Paper),
+                (2, crate::ActionKind::Scissors),
+            ] {
+                game_data.current_round.inputs.insert(player_id, action);
+                game_data.current_round.submitted_at.insert(player_id, 1000);
+            }
+
+            let serialized =
+                serde_json::to_string(&LaunchGetGameResponse::from(game_data.clone())).unwrap();
+
+            //actions serialize as quoted strings, unlike the RockPaperScissors kind
+            for action in [r#""Rock""#, r#""Paper""#, r#""Scissors""#] {
+                assert!(
+                    !serialized.contains(action),
+                    "{action} leaked in {serialized}"
+                );
+            }
+
+            //a player only gets their own action back
+            let player_view =
+                serde_json::to_value(LaunchGetGameResponse::for_player(game_data, Some(0)))
+                    .unwrap();
+            assert_eq!(player_view["your_current_action"], "Rock");
+            let serialized = player_view.to_string();
+            for action in [r#""Paper""#, r#""Scissors""#] {
+                assert!(
+                    !serialized.contains(action),
+                    "{action} leaked in {serialized}"
+                );
+            }
+        }
+
+        #[test]
+        fn test_spectator_response_strips_inputs() {
+            let mut game_data = game_with_players(&["Alice", "Bob"]);
+            game_data.observers.push(player(2, "Charlie"));
+            game_data
+                .current_round
+                .inputs
+                .insert(0, crate::ActionKind::Paper);
+
+            let mut spectator = serde_json::to_value(game_data.to_spectator_response(0)).unwrap();
+            let player_view = serde_json::to_value(LaunchGetGameResponse::from(game_data)).unwrap();
+
+            assert!(!spectator.to_string().contains(r#""Paper""#));
+            assert_eq!(spectator["waiting_count"], 1);
+
+            let observers = spectator.as_object_mut().unwrap().remove("observers");
+            assert_eq!(observers.unwrap()[0]["name"], "Charlie");
+            let delayed_actions = spectator.as_object_mut().unwrap().remove("delayed_actions");
+            assert_eq!(delayed_actions.unwrap(), serde_json::json!({}));
+            assert_eq!(spectator, player_view);
+        }
+
+        #[test]
+        fn test_hidden_waiting_players() {
+            let mut game_data = game_with_players(&["Alice", "Bob", "Charlie"]);
+            game_data
+                .current_round
+                .inputs
+                .insert(0, crate::ActionKind::Rock);
+
+            let response = LaunchGetGameResponse::from(game_data.clone());
+            assert_eq!(response.waiting_for_players.len(), 2);
+            assert_eq!(response.waiting_count, 2);
+
+            game_data.settings.hide_waiting_players = true;
+            let response = LaunchGetGameResponse::from(game_data);
+            assert!(response.waiting_for_players.is_empty());
+            assert_eq!(response.waiting_count, 2);
+        }
+
+        #[test]
+        fn test_scores_map() {
+            let mut game_data = game_with_players(&["Alice", "Bob"]);
+            game_data.players[0].1 = 2;
+            game_data.players[1].1 = 1;
+
+            let response = LaunchGetGameResponse::from(game_data.clone()).with_scores_map(false);
+            let serialized = serde_json::to_value(response).unwrap();
+            assert!(serialized.get("scores").is_none());
+
+            let response = LaunchGetGameResponse::from(game_data).with_scores_map(true);
+            let serialized = serde_json::to_value(response).unwrap();
+            assert_eq!(
+                serialized["scores"],
+                serde_json::json!({"Alice": 2, "Bob": 1})
+            );
+            //the tuple list is still there for older clients
+            assert_eq!(serialized["players"].as_array().unwrap().len(), 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    pub fn player(id: PlayerId, name: &str) -> PlayerData {
+        PlayerData {
+            id,
+            name: name.to_string(),
+            region: None,
+            friends: vec![],
+        }
+    }
+
+    //A running game of the default settings, between players given ids from 0
+    pub fn game_with_players(names: &[&str]) -> GameData {
+        GameData {
+            id: 0,
+            settings: GameSettings {
+                player_count: names.len(),
+                ..GameSettings::default()
+            },
+            players: names
+                .iter()
+                .zip(0..)
+                .map(|(name, id)| (player(id, name), 0))
+                .collect(),
+            current_round: RoundData::default(),
+            round_history: vec![],
+            status: GameStatus::Running,
+            winner: None,
+            winners: vec![],
+            observers: vec![],
+            eliminated: vec![],
+            forfeited: vec![],
+            version: 0,
+            board: None,
+            round_started_at: 0,
+            paused_at: None,
+            ended_at: None,
+            tie_break_rounds: 0,
+            submission_keys: BTreeMap::new(),
+        }
+    }
+
+    //Must pass with and without the serde_with feature, the client and the
+    //server have to agree on the format
+    #[test]
+    fn test_settings_wire_format() {
+        let settings = GameSettings {
+            kind: GameKind::RockPaperScissors,
+            player_count: 2,
+            end_condition: EndCondition::FirstToScore(3),
+            allow_action_change: true,
+            hide_waiting_players: false,
+            action_cooldown_ms: Some(500),
+            round_timeout_secs: None,
+            spectator_delay_secs: None,
+            scoring_mode: ScoringMode::Pairwise,
+            tie_break: TieBreak::Draw,
+            max_rounds: 100,
+        };
+
+        let serialized = serde_json::to_value(&settings).unwrap();
+        assert_eq!(serialized["player_count"], "2");
+        assert_eq!(serialized["allow_action_change"], "true");
+        assert_eq!(serialized["action_cooldown_ms"], "500");
+
+        let deserialized: GameSettings = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, settings);
+
+        let without_cooldown: GameSettings = serde_json::from_str(
+            r#"{"kind":"RockPaperScissors","player_count":"2","end_condition":{"FirstToScore":3}}"#,
+        )
+        .unwrap();
+        assert_eq!(without_cooldown.action_cooldown_ms, None);
+        assert!(without_cooldown.allow_action_change);
+    }
+
+    #[test]
+    fn test_partial_settings() {
+        let settings: GameSettings =
+            serde_json::from_str(r#"{"end_condition":{"TotalRounds":5}}"#).unwrap();
+        assert_eq!(
+            settings,
+            GameSettings {
+                end_condition: EndCondition::TotalRounds(5),
+                ..GameSettings::default()
+            }
+        );
+        assert_eq!(settings.kind, GameKind::RockPaperScissors);
+        assert_eq!(settings.player_count, 2);
+    }
+
+    #[test]
+    fn test_required_submissions_remaining() {
+        let mut game_data = game_with_players(&["Alice", "Bob", "Charlie"]);
+        game_data.settings.scoring_mode = ScoringMode::Survival;
+        assert_eq!(game_data.required_submissions_remaining(), 3);
+
+        game_data.current_round.inputs.insert(0, ActionKind::Rock);
+        assert_eq!(game_data.required_submissions_remaining(), 2);
+
+        //charlie forfeited in an earlier round
+        game_data.forfeited.push(2);
+        assert_eq!(game_data.required_submissions_remaining(), 1);
+
+        //bob was knocked out
+        game_data.eliminated.push(1);
+        assert_eq!(game_data.required_submissions_remaining(), 0);
+    }
+
+    #[test]
+    fn test_estimated_duration() {
+        let settings = |end_condition| GameSettings {
+            end_condition,
+            ..GameSettings::default()
+        };
+
+        assert!(
+            settings(EndCondition::TotalRounds(10)).estimated_duration()
+                > settings(EndCondition::FirstToScore(1)).estimated_duration()
+        );
+    }
+
+    #[test]
+    fn test_round_serialization_is_stable() {
+        let mut first_round = RoundData::default();
+        first_round.inputs.insert(2, ActionKind::Rock);
+        first_round.inputs.insert(0, ActionKind::Paper);
+        first_round.inputs.insert(1, ActionKind::Scissors);
+
+        let mut second_round = RoundData::default();
+        second_round.inputs.insert(1, ActionKind::Scissors);
+        second_round.inputs.insert(2, ActionKind::Rock);
+        second_round.inputs.insert(0, ActionKind::Paper);
+
+        let serialized = serde_json::to_string(&first_round).unwrap();
+        assert_eq!(serialized, serde_json::to_string(&first_round).unwrap());
+        assert_eq!(serialized, serde_json::to_string(&second_round).unwrap());
+        assert!(serialized.starts_with(r#"{"inputs":{"0":"Paper","1":"Scissors","2":"Rock"}"#));
+    }
+
+    #[test]
+    fn test_player_data_round_trip() {
+        let player_data = PlayerData {
+            id: 3,
+            name: "Alice".to_string(),
+            region: None,
+            friends: vec![],
+        };
+
+        let serialized = serde_json::to_string(&player_data).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PlayerData>(&serialized).unwrap(),
+            player_data
+        );
+    }
+
+    #[test]
+    fn test_room_data_round_trip() {
+        let room_data = RoomData {
+            id: 1,
+            name: "test room".to_string(),
+            settings: GameSettings {
+                end_condition: EndCondition::TotalRounds(5),
+                allow_action_change: false,
+                hide_waiting_players: true,
+                action_cooldown_ms: Some(500),
+                ..GameSettings::default()
+            },
+            host_id: 3,
+            players: vec![player(3, "Alice")],
+            spectators: vec![player(5, "Bob")],
+            reserved_seats: vec![(4, 1000)],
+            ready: vec![],
+            visibility: RoomVisibility::Public,
+            password: Some("secret".to_string()),
+        };
+
+        let serialized = serde_json::to_string(&room_data).unwrap();
+        assert_eq!(
+            serde_json::from_str::<RoomData>(&serialized).unwrap(),
+            room_data
+        );
+    }
+
+    #[test]
+    fn test_bot_strategy_from_clients() {
+        assert_eq!(
+            serde_json::from_str::<BotStrategy>(r#""CounterLast""#).unwrap(),
+            BotStrategy::CounterLast
+        );
+        //replays are only built by the server
+        assert!(serde_json::from_str::<BotStrategy>(r#"{"Replay":[]}"#).is_err());
+        assert!(serde_json::from_str::<BotStrategy>(r#"{"Replay":["Lizard"]}"#).is_err());
+
+        let rock_paper_scissors = GameKind::RockPaperScissors;
+        assert!(BotStrategy::AlwaysRock.is_available_in(&rock_paper_scissors));
+        assert!(BotStrategy::Replay(vec![ActionKind::Paper]).is_available_in(&rock_paper_scissors));
+        assert!(!BotStrategy::Replay(vec![]).is_available_in(&rock_paper_scissors));
+        assert!(
+            !BotStrategy::Replay(vec![ActionKind::Lizard]).is_available_in(&rock_paper_scissors)
+        );
+        assert!(!BotStrategy::Cycle.is_available_in(&GameKind::Chess));
     }
 }
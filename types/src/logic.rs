@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use crate::{ActionKind, PlayerId, RoundResult};
+
+//Rules of a game kind. The scoring modes are built on top of them, so a new
+//game only has to say which actions exist and which ones win
+pub trait GameLogic {
+    fn available_actions(&self) -> Vec<ActionKind>;
+
+    //Every pair of players duels each round, past a few players a round
+    //would take forever to read
+    fn player_counts(&self) -> RangeInclusive<usize> {
+        2..=8
+    }
+
+    fn beats(&self, action: &ActionKind, other: &ActionKind) -> bool;
+
+    fn is_action_valid(&self, action: &ActionKind) -> bool {
+        self.available_actions().contains(action)
+    }
+
+    //Every pair of players duels, in id order so the results always come out
+    //in the same order
+    fn resolve_round(&self, inputs: &BTreeMap<PlayerId, ActionKind>) -> Vec<RoundResult> {
+        let inputs = inputs.iter().collect::<Vec<_>>();
+
+        inputs
+            .iter()
+            .enumerate()
+            .flat_map(|(index, p1)| inputs[index + 1..].iter().map(move |p2| (p1, p2)))
+            .map(|((p1_id, p1_action), (p2_id, p2_action))| {
+                let winner = if self.beats(p1_action, p2_action) {
+                    Some(**p1_id)
+                } else if self.beats(p2_action, p1_action) {
+                    Some(**p2_id)
+                } else {
+                    None
+                };
+
+                RoundResult::Duel {
+                    players: [**p1_id, **p2_id],
+                    winner,
+                }
+            })
+            .collect()
+    }
+}
+
+const RPS_ACTIONS: [ActionKind; 3] = [ActionKind::Rock, ActionKind::Paper, ActionKind::Scissors];
+
+pub struct RpsLogic;
+
+impl GameLogic for RpsLogic {
+    fn available_actions(&self) -> Vec<ActionKind> {
+        RPS_ACTIONS.to_vec()
+    }
+
+    fn beats(&self, action: &ActionKind, other: &ActionKind) -> bool {
+        action.beats(other)
+    }
+}
+
+pub struct RpslsLogic;
+
+impl GameLogic for RpslsLogic {
+    fn available_actions(&self) -> Vec<ActionKind> {
+        let mut actions = RPS_ACTIONS.to_vec();
+        actions.extend([ActionKind::Lizard, ActionKind::Spock]);
+        actions
+    }
+
+    fn beats(&self, action: &ActionKind, other: &ActionKind) -> bool {
+        action.beats(other)
+    }
+}
+
+//Rock paper scissors with a custom win table
+pub struct CustomLogic<'a> {
+    pub wins: &'a [(ActionKind, ActionKind)],
+}
+
+impl GameLogic for CustomLogic<'_> {
+    fn available_actions(&self) -> Vec<ActionKind> {
+        RPS_ACTIONS.to_vec()
+    }
+
+    fn beats(&self, action: &ActionKind, other: &ActionKind) -> bool {
+        self.wins.contains(&(action.clone(), other.clone()))
+    }
+}
+
+//Moves depend on the board, they are checked against it by the server, and
+//no move beats another one
+pub struct ChessLogic;
+
+impl GameLogic for ChessLogic {
+    fn available_actions(&self) -> Vec<ActionKind> {
+        vec![]
+    }
+
+    fn player_counts(&self) -> RangeInclusive<usize> {
+        2..=2
+    }
+
+    fn beats(&self, _action: &ActionKind, _other: &ActionKind) -> bool {
+        false
+    }
+
+    fn is_action_valid(&self, action: &ActionKind) -> bool {
+        matches!(action, ActionKind::Move { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rps_resolve_round() {
+        let inputs = BTreeMap::from([
+            (2, ActionKind::Scissors),
+            (0, ActionKind::Rock),
+            (1, ActionKind::Rock),
+        ]);
+
+        assert_eq!(
+            RpsLogic.resolve_round(&inputs),
+            vec![
+                RoundResult::Duel {
+                    players: [0, 1],
+                    winner: None
+                },
+                RoundResult::Duel {
+                    players: [0, 2],
+                    winner: Some(0)
+                },
+                RoundResult::Duel {
+                    players: [1, 2],
+                    winner: Some(1)
+                },
+            ]
+        );
+        assert!(!ChessLogic.is_action_valid(&ActionKind::Rock));
+    }
+}